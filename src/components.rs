@@ -1,7 +1,66 @@
+use crate::resources::{MusicTrack, Settings};
 use bevy::prelude::*;
+use bevy_xpbd_2d::prelude::*;
 
-#[derive(Component)]
-pub struct IntroScreen {}
+/// Whether bullets can hit someone on the side that fired them.
+pub const FRIENDLY_FIRE: bool = false;
+
+/// The collision groups. Which of them touch each other is declared once, in
+/// `Layer::INTERACTIONS`.
+#[derive(PhysicsLayer, Clone, Copy, PartialEq, Eq)]
+pub enum Layer {
+    Player,
+    Enemy,
+    PlayerProjectile,
+    EnemyProjectile,
+    Shield,
+    Pickup,
+}
+
+impl Layer {
+    const ALL: [Layer; 6] = [
+        Layer::Player,
+        Layer::Enemy,
+        Layer::PlayerProjectile,
+        Layer::EnemyProjectile,
+        Layer::Shield,
+        Layer::Pickup,
+    ];
+
+    /// Pairs of layers that collide. The order within a pair doesn't matter.
+    const INTERACTIONS: [(Layer, Layer); 5] = [
+        (Layer::PlayerProjectile, Layer::Enemy),
+        (Layer::PlayerProjectile, Layer::Shield),
+        (Layer::EnemyProjectile, Layer::Player),
+        (Layer::EnemyProjectile, Layer::Shield),
+        (Layer::Player, Layer::Pickup),
+    ];
+
+    /// Only used when `FRIENDLY_FIRE` is on.
+    const FRIENDLY_FIRE_INTERACTIONS: [(Layer, Layer); 2] = [
+        (Layer::PlayerProjectile, Layer::Player),
+        (Layer::EnemyProjectile, Layer::Enemy),
+    ];
+
+    fn interacts_with(self, other: Layer) -> bool {
+        let friendly_fire: &[(Layer, Layer)] = match FRIENDLY_FIRE {
+            true => &Layer::FRIENDLY_FIRE_INTERACTIONS,
+            false => &[],
+        };
+        Layer::INTERACTIONS
+            .iter()
+            .chain(friendly_fire)
+            .any(|&(a, b)| (a == self && b == other) || (a == other && b == self))
+    }
+
+    /// The collision layers for an entity in this group.
+    pub fn collision_layers(self) -> CollisionLayers {
+        let masks = Layer::ALL
+            .into_iter()
+            .filter(|other| self.interacts_with(*other));
+        CollisionLayers::new([self], masks)
+    }
+}
 
 #[derive(Component)]
 pub struct GameScreen {}
@@ -9,26 +68,214 @@ pub struct GameScreen {}
 #[derive(Component)]
 pub struct GameOverScreen {}
 
+/// The music track currently playing, or one fading out after a switch.
 #[derive(Component)]
-pub struct MenuMusic {}
+pub struct Music {
+    pub track: MusicTrack,
+    pub fade: f32,
+    pub fading_out: bool,
+}
 
+/// A layer of music on top of its track, fading in with the intensity.
 #[derive(Component)]
-pub struct GameStartMusic {}
+pub struct IntensityLayer;
+
+/// The volume bus a sound plays on.
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+pub enum AudioBus {
+    Music,
+    Sfx,
+    Voice,
+}
+
+impl AudioBus {
+    pub fn volume(&self, settings: &Settings) -> f32 {
+        let bus_volume = match self {
+            AudioBus::Music => settings.music_volume,
+            AudioBus::Sfx => settings.sfx_volume,
+            AudioBus::Voice => settings.voice_volume,
+        };
+        settings.master_volume * bus_volume
+    }
+}
 
+/// A sound effect waiting to be mixed in by `play_sound_effects`.
 #[derive(Component)]
-pub struct GameOverMusic {}
+pub struct SoundEffect {
+    pub source: Handle<AudioSource>,
+    pub volume: f32,
+    pub pitch_variation: f32,
+    /// Where the sound comes from, for stereo panning. `None` plays centered.
+    pub position: Option<Vec3>,
+}
+
+/// A sound effect that is currently playing.
+#[derive(Component)]
+pub struct PlayingSound {
+    pub clip: AssetId<AudioSource>,
+    pub started: f32,
+    /// Volume before the bus and master volume are applied.
+    pub volume: f32,
+}
+
+/// Gives the entity a collider fitted to the opaque pixels of its sprite once
+/// the image has loaded, unless `colliders.ron` overrides it.
+#[derive(Component, Clone, Copy)]
+pub enum SpriteCollider {
+    Cuboid,
+    Ball,
+}
+
+/// One frame of an animation clip: a cell of the entity's texture atlas, plus
+/// a flip and a tint. The sheets in `assets/sprites` have a single cell each
+/// for now, so every frame uses index 0 and differs only by flip and tint.
+pub struct AnimationFrame {
+    pub index: usize,
+    pub flip_x: bool,
+    pub tint: Color,
+}
+
+const fn frame(index: usize, flip_x: bool, tint: Color) -> AnimationFrame {
+    AnimationFrame {
+        index,
+        flip_x,
+        tint,
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SpriteClip {
+    EnemyMarch,
+    EnemyShoot,
+    PlayerIdle,
+    PlayerThrust,
+    PlayerHit,
+    CastleIntact,
+    CastleDamaged,
+}
+
+impl SpriteClip {
+    pub fn frames(&self) -> &'static [AnimationFrame] {
+        const HIT: Color = Color::rgb(1.0, 0.2, 0.2);
+        const DAMAGED: Color = Color::rgb(0.6, 0.45, 0.45);
+        const THRUST: Color = Color::rgb(1.0, 0.85, 0.7);
+
+        const ENEMY_MARCH: [AnimationFrame; 2] =
+            [frame(0, false, Color::WHITE), frame(0, true, Color::WHITE)];
+        const ENEMY_SHOOT: [AnimationFrame; 2] = [
+            frame(0, false, Color::YELLOW),
+            frame(0, false, Color::WHITE),
+        ];
+        const PLAYER_IDLE: [AnimationFrame; 1] = [frame(0, false, Color::WHITE)];
+        const PLAYER_THRUST: [AnimationFrame; 2] =
+            [frame(0, false, Color::WHITE), frame(0, false, THRUST)];
+        const PLAYER_HIT: [AnimationFrame; 4] = [
+            frame(0, false, HIT),
+            frame(0, false, Color::WHITE),
+            frame(0, false, HIT),
+            frame(0, false, Color::WHITE),
+        ];
+        const CASTLE_INTACT: [AnimationFrame; 1] = [frame(0, false, Color::WHITE)];
+        const CASTLE_DAMAGED: [AnimationFrame; 1] = [frame(0, false, DAMAGED)];
+
+        match self {
+            SpriteClip::EnemyMarch => &ENEMY_MARCH,
+            SpriteClip::EnemyShoot => &ENEMY_SHOOT,
+            SpriteClip::PlayerIdle => &PLAYER_IDLE,
+            SpriteClip::PlayerThrust => &PLAYER_THRUST,
+            SpriteClip::PlayerHit => &PLAYER_HIT,
+            SpriteClip::CastleIntact => &CASTLE_INTACT,
+            SpriteClip::CastleDamaged => &CASTLE_DAMAGED,
+        }
+    }
+
+    /// The damage stage of a castle with `hitpoints` left.
+    pub fn for_castle(hitpoints: u32) -> SpriteClip {
+        match hitpoints {
+            0 | 1 => SpriteClip::CastleDamaged,
+            _ => SpriteClip::CastleIntact,
+        }
+    }
+
+    /// Frames per second. The enemy march is stepped by the formation instead.
+    pub fn fps(&self) -> f32 {
+        match self {
+            SpriteClip::EnemyMarch => 0.0,
+            SpriteClip::EnemyShoot => 6.0,
+            SpriteClip::PlayerThrust => 12.0,
+            SpriteClip::PlayerHit => 10.0,
+            _ => 1.0,
+        }
+    }
+
+    /// The clip to go back to once a one-shot clip has finished.
+    pub fn then(&self) -> Option<SpriteClip> {
+        match self {
+            SpriteClip::EnemyShoot => Some(SpriteClip::EnemyMarch),
+            SpriteClip::PlayerHit => Some(SpriteClip::PlayerIdle),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct SpriteAnimation {
+    pub clip: SpriteClip,
+    pub frame: usize,
+    pub timer: Timer,
+}
+
+impl SpriteAnimation {
+    pub fn new(clip: SpriteClip) -> SpriteAnimation {
+        let mut animation = SpriteAnimation {
+            clip,
+            frame: 0,
+            timer: Timer::from_seconds(1.0, TimerMode::Repeating),
+        };
+        animation.play(clip);
+        animation
+    }
+
+    /// Starts `clip` from its first frame.
+    pub fn play(&mut self, clip: SpriteClip) {
+        self.clip = clip;
+        self.frame = 0;
+        if clip.fps() > 0.0 {
+            self.timer = Timer::from_seconds(1.0 / clip.fps(), TimerMode::Repeating);
+        }
+    }
+
+    /// Advances to the next frame, moving on to the follow-up clip at the end
+    /// of a one-shot clip.
+    pub fn step(&mut self) {
+        self.frame += 1;
+        if self.frame < self.clip.frames().len() {
+            return;
+        }
+
+        match self.clip.then() {
+            Some(next) => self.play(next),
+            None => self.frame = 0,
+        }
+    }
+
+    pub fn current_frame(&self) -> &'static AnimationFrame {
+        &self.clip.frames()[self.frame]
+    }
+}
 
 #[derive(Component)]
 pub struct Player {}
 
 #[derive(Component)]
 pub struct Bullet {
-    pub speed: f32,
+    /// Bullets never hit whoever fired them, even with `FRIENDLY_FIRE` on.
+    pub shooter: Entity,
 }
 
 #[derive(Component)]
 pub struct EnemyBullet {
-    pub speed: f32,
+    pub shooter: Entity,
 }
 
 #[derive(Component)]
@@ -40,10 +287,311 @@ pub struct Castle {
 pub struct Enemy {
     pub level: u32,
     pub is_dead: bool,
+    /// Index into the `EnemyCatalog`.
+    pub kind: usize,
+}
+
+/// A killed enemy spinning away before it is despawned.
+#[derive(Component)]
+pub struct Dying {
+    pub timer: Timer,
+}
+
+/// Floating "+points" text that drifts up from a kill and fades out.
+#[derive(Component)]
+pub struct ScorePopup {
+    pub timer: Timer,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ParticleEffect {
+    EnemyBurst,
+    CastleSparks,
+    CastleDebris,
+    MuzzleFlash,
+}
+
+/// How a burst of particles looks. Angles are in radians, with 0 pointing
+/// right and `spread` the total width of the cone particles fly out in.
+pub struct ParticleBurst {
+    pub count: usize,
+    pub direction: f32,
+    pub spread: f32,
+    pub speed: (f32, f32),
+    pub lifetime: (f32, f32),
+    pub size: (f32, f32),
+    pub color: (Color, Color),
+    pub gravity: f32,
+}
+
+impl ParticleEffect {
+    pub fn burst(&self) -> ParticleBurst {
+        use std::f32::consts::{FRAC_PI_2, PI, TAU};
+
+        match self {
+            ParticleEffect::EnemyBurst => ParticleBurst {
+                count: 24,
+                direction: 0.0,
+                spread: TAU,
+                speed: (60.0, 220.0),
+                lifetime: (0.3, 0.7),
+                size: (5.0, 1.0),
+                color: (Color::rgb(1.0, 0.9, 0.4), Color::rgba(1.0, 0.3, 0.1, 0.0)),
+                gravity: 0.0,
+            },
+            ParticleEffect::CastleSparks => ParticleBurst {
+                count: 10,
+                direction: FRAC_PI_2,
+                spread: PI,
+                speed: (80.0, 200.0),
+                lifetime: (0.15, 0.35),
+                size: (3.0, 1.0),
+                color: (Color::WHITE, Color::rgba(1.0, 0.8, 0.3, 0.0)),
+                gravity: -300.0,
+            },
+            ParticleEffect::CastleDebris => ParticleBurst {
+                count: 32,
+                direction: FRAC_PI_2,
+                spread: PI * 0.8,
+                speed: (100.0, 280.0),
+                lifetime: (0.6, 1.2),
+                size: (7.0, 3.0),
+                color: (Color::rgb(0.6, 0.55, 0.5), Color::rgba(0.3, 0.25, 0.2, 0.0)),
+                gravity: -500.0,
+            },
+            ParticleEffect::MuzzleFlash => ParticleBurst {
+                count: 6,
+                direction: FRAC_PI_2,
+                spread: PI / 3.0,
+                speed: (40.0, 120.0),
+                lifetime: (0.05, 0.12),
+                size: (6.0, 2.0),
+                color: (Color::rgb(1.0, 1.0, 0.8), Color::rgba(1.0, 0.6, 0.2, 0.0)),
+                gravity: 0.0,
+            },
+        }
+    }
+}
+
+/// Emits one burst of `effect` at its transform, then goes away.
+#[derive(Component)]
+pub struct ParticleEmitter {
+    pub effect: ParticleEffect,
+}
+
+#[derive(Component)]
+pub struct Particle {
+    pub velocity: Vec2,
+    pub gravity: f32,
+    pub age: f32,
+    pub lifetime: f32,
+    pub size: (f32, f32),
+    pub color: (Color, Color),
+}
+
+/// The UI node covering the letterboxed playfield. Every other UI root is
+/// moved into it, so menus and the HUD line up with the game.
+#[derive(Component)]
+pub struct PlayfieldUi;
+
+/// Trauma-based camera shake. Trauma is added by hits and decays over time;
+/// the offset grows with the square of it.
+#[derive(Component)]
+pub struct CameraShake {
+    pub trauma: f32,
+    pub origin: Vec3,
+}
+
+/// Makes a sprite blink white for a moment after it takes damage.
+#[derive(Component)]
+pub struct DamageFlash {
+    pub timer: Timer,
+}
+
+/// A star or nebula cloud in the scrolling background, moving down at
+/// `speed` pixels per second and wrapping back to the top.
+#[derive(Component)]
+pub struct Star {
+    pub speed: f32,
 }
 
 #[derive(Component)]
 pub struct ScoreText;
 
 #[derive(Component)]
-pub struct LivesCounter;
+pub struct Hud;
+
+#[derive(Component)]
+pub struct ScoreHud;
+
+#[derive(Component)]
+pub struct HighScoreHud;
+
+#[derive(Component)]
+pub struct WaveHud;
+
+/// The untranslated label in the first section of a HUD text.
+#[derive(Component)]
+pub struct HudLabel(pub &'static str);
+
+#[derive(Component)]
+pub struct LivesHud;
+
+#[derive(Component)]
+pub struct InitialsScreen;
+
+#[derive(Component)]
+pub struct InitialsLetter {
+    pub index: usize,
+}
+
+#[derive(Component)]
+pub struct HighScoreTableScreen;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PauseAction {
+    Resume,
+    Restart,
+    Options,
+    QuitToTitle,
+}
+
+impl PauseAction {
+    pub const ALL: [PauseAction; 4] = [
+        PauseAction::Resume,
+        PauseAction::Restart,
+        PauseAction::Options,
+        PauseAction::QuitToTitle,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            PauseAction::Resume => "Resume",
+            PauseAction::Restart => "Restart",
+            PauseAction::Options => "Options",
+            PauseAction::QuitToTitle => "Quit to title",
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct PauseMenu {
+    pub selected: usize,
+}
+
+#[derive(Component)]
+pub struct PauseMenuItem {
+    pub action: PauseAction,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum MenuAction {
+    Start,
+    Mode,
+    HighScores,
+    Options,
+    Credits,
+    Quit,
+}
+
+impl MenuAction {
+    pub const ALL: [MenuAction; 6] = [
+        MenuAction::Start,
+        MenuAction::Mode,
+        MenuAction::HighScores,
+        MenuAction::Options,
+        MenuAction::Credits,
+        MenuAction::Quit,
+    ];
+}
+
+#[derive(Component)]
+pub struct MainMenu {
+    pub selected: usize,
+}
+
+#[derive(Component)]
+pub struct MainMenuTitle;
+
+#[derive(Component)]
+pub struct MainMenuItem {
+    pub action: MenuAction,
+}
+
+#[derive(Component)]
+pub struct CreditsScreen;
+
+#[derive(Component)]
+pub struct CountdownScreen;
+
+#[derive(Component)]
+pub struct CountdownText;
+
+#[derive(Component)]
+pub struct LoadingScreen;
+
+#[derive(Component)]
+pub struct LoadingBar;
+
+#[derive(Component)]
+pub struct LoadingText;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum OptionKind {
+    MasterVolume,
+    MusicVolume,
+    SfxVolume,
+    VoiceVolume,
+    Fullscreen,
+    Vsync,
+    ScreenShake,
+    HitStop,
+    DamageFlash,
+    Starfield,
+    Language,
+    Back,
+}
+
+impl OptionKind {
+    pub const ALL: [OptionKind; 12] = [
+        OptionKind::MasterVolume,
+        OptionKind::MusicVolume,
+        OptionKind::SfxVolume,
+        OptionKind::VoiceVolume,
+        OptionKind::Fullscreen,
+        OptionKind::Vsync,
+        OptionKind::ScreenShake,
+        OptionKind::HitStop,
+        OptionKind::DamageFlash,
+        OptionKind::Starfield,
+        OptionKind::Language,
+        OptionKind::Back,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            OptionKind::MasterVolume => "Master volume",
+            OptionKind::MusicVolume => "Music volume",
+            OptionKind::SfxVolume => "Effects volume",
+            OptionKind::VoiceVolume => "Voice volume",
+            OptionKind::Fullscreen => "Fullscreen",
+            OptionKind::Vsync => "Vsync",
+            OptionKind::ScreenShake => "Screen shake",
+            OptionKind::HitStop => "Hit-stop",
+            OptionKind::DamageFlash => "Damage flash",
+            OptionKind::Starfield => "Starfield",
+            OptionKind::Language => "Language",
+            OptionKind::Back => "Back",
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct OptionsScreen {
+    pub selected: usize,
+}
+
+#[derive(Component)]
+pub struct OptionsItem {
+    pub option: OptionKind,
+}
@@ -1,3 +1,4 @@
+use crate::resources::MusicTrack;
 use bevy::prelude::*;
 
 #[derive(Event)]
@@ -9,3 +10,79 @@ pub struct GameOver {
     pub score: u32,
     pub won: bool,
 }
+
+#[derive(Event)]
+pub struct ScoreChanged {
+    pub value: u32,
+}
+
+#[derive(Event)]
+pub struct LivesChanged {
+    pub value: u32,
+}
+
+#[derive(Event)]
+pub struct WaveStarted {
+    pub wave: u32,
+}
+
+#[derive(Event)]
+pub struct BulletFired {
+    pub shooter: Entity,
+    pub position: Vec3,
+    pub from_player: bool,
+}
+
+#[derive(Event)]
+pub struct EnemyKilled {
+    pub enemy: Entity,
+    /// False when the enemy was shot by another enemy.
+    pub by_player: bool,
+    pub enemy_kind: usize,
+    pub level: u32,
+    pub position: Vec3,
+}
+
+#[derive(Event)]
+pub struct PlayerHit {
+}
+
+#[derive(Event)]
+pub struct CastleDamaged {
+    pub castle: Entity,
+    pub hitpoints: u32,
+    pub position: Vec3,
+}
+
+#[derive(Event)]
+pub struct CastleDestroyed {
+    pub castle: Entity,
+    pub position: Vec3,
+}
+
+#[derive(Event)]
+pub struct WaveCleared {
+    pub wave: u32,
+}
+
+/// Points scored for a single kill, at the spot where it happened.
+#[derive(Event)]
+pub struct PointsAwarded {
+    pub points: u32,
+    pub position: Vec3,
+}
+
+#[derive(Event)]
+pub struct ProjectileDestroyed {
+    pub projectile: Entity,
+    pub position: Vec3,
+}
+
+#[derive(Event)]
+pub struct CountdownRequested {
+}
+
+#[derive(Event)]
+pub struct PlayMusic {
+    pub track: MusicTrack,
+}
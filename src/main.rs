@@ -8,53 +8,102 @@ use events::*;
 use resources::*;
 use systems::*;
 
+use bevy::audio::AddAudioSource;
 use bevy::prelude::*;
-use bevy::window::{PresentMode, WindowTheme};
+use bevy::window::{WindowResizeConstraints, WindowTheme};
+use bevy::winit::WinitSettings;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 
 use bevy_xpbd_2d::prelude::*;
 use bevy_xpbd_2d::{math::*, prelude::*};
 
 fn main() {
-    App::new()
-        .add_plugins(PhysicsPlugins::default())
+    let check_assets_mode = std::env::args().any(|arg| arg == "--check-assets");
+    let high_score_table = HighScoreTable::load();
+    // Window settings have to be known before the window opens.
+    let settings = Settings::load();
+
+    let mut app = App::new();
+    app.add_plugins(PhysicsPlugins::default())
         .insert_resource(Gravity(Vector::ZERO))
         .add_plugins(DefaultPlugins.set(WindowPlugin {
             primary_window: Some(Window {
                 title: "Safe space invaders!".into(),
-                resolution: (WINDOW_HEIGHT, WINDOW_WIDTH).into(),
-                present_mode: PresentMode::AutoVsync,
+                resolution: (PLAYFIELD_WIDTH, PLAYFIELD_HEIGHT).into(),
+                resize_constraints: WindowResizeConstraints {
+                    min_width: PLAYFIELD_WIDTH / 2.0,
+                    min_height: PLAYFIELD_HEIGHT / 2.0,
+                    ..default()
+                },
+                present_mode: settings.present_mode(),
+                mode: settings.window_mode(),
                 // Tells wasm to resize the window according to the available canvas
                 fit_canvas_to_parent: true,
                 // Tells wasm not to override default event handling, like F5, Ctrl+R etc.
                 prevent_default_event_handling: false,
                 window_theme: Some(WindowTheme::Dark),
-                enabled_buttons: bevy::window::EnabledButtons {
-                    maximize: false,
-                    ..Default::default()
-                },
-                visible: true,
+                visible: !check_assets_mode,
                 ..default()
             }),
             ..default()
         }))
+        .insert_resource(ClearColor(Color::BLACK))
+        .add_audio_source::<MarchBeat>()
         .init_resource::<Game>()
         .init_resource::<Score>()
-        .init_resource::<HighScore>()
+        .insert_resource(HighScore {
+            value: high_score_table.best(),
+        })
+        .insert_resource(high_score_table)
+        .insert_resource(settings)
         .init_resource::<Lives>()
+        .init_resource::<Wave>()
+        .init_resource::<GameMode>()
+        .init_resource::<MusicState>()
+        .init_resource::<Countdown>()
+        .init_resource::<GameStats>()
+        .init_resource::<ParticlePool>()
+        .init_resource::<HitStop>()
+        .insert_resource(ColliderOverrides::load())
         .init_resource::<EnemyInfo>()
         .init_resource::<EnemyCatalog>()
+        .init_resource::<GameAssets>()
         .init_resource::<LoadingFlags>()
         .add_event::<GameOver>()
         .add_event::<GameStartRequested>()
+        .add_event::<ScoreChanged>()
+        .add_event::<LivesChanged>()
+        .add_event::<WaveStarted>()
+        .add_event::<PlayMusic>()
+        .add_event::<CountdownRequested>()
+        .add_event::<EnemyKilled>()
+        .add_event::<PlayerHit>()
+        .add_event::<BulletFired>()
+        .add_event::<CastleDamaged>()
+        .add_event::<CastleDestroyed>()
+        .add_event::<WaveCleared>()
+        .add_event::<PointsAwarded>()
+        .add_event::<ProjectileDestroyed>()
         .add_systems(Startup, spawn_camera)
+        .add_systems(Update, letterbox_camera)
+        .add_systems(Update, confine_ui_to_playfield)
         .add_systems(Startup, spawn_game_intro)
         .add_systems(Startup, start_menu_music)
+        .add_systems(Startup, spawn_hud)
         .add_systems(Update, spawn_game_background)
+        .add_systems(Update, scroll_starfield)
+        .add_systems(Update, change_starfield_theme)
         .add_systems(Update, spawn_bullet)
-        .add_systems(Update, bullet_hits_enemy)
-        .add_systems(Update, move_bullet)
-        .add_systems(Update, move_enemy_bullet)
-        .add_systems(Update, setup_lives)
+        .add_systems(Update, despawn_offscreen_projectiles)
+        .add_systems(Update, build_sprite_sheets)
+        .add_systems(Update, fit_sprite_colliders)
+        .add_systems(Update, march_enemies)
+        .add_systems(Update, trigger_animations.after(resolve_collisions))
+        .add_systems(Update, animate_player_thrust)
+        .add_systems(Update, animate_sprites.after(trigger_animations))
+        .add_systems(Update, sweep_projectiles)
+        .add_systems(Update, show_hud)
         .add_systems(Update, start_game)
         .add_systems(Update, spawn_player)
         .add_systems(Update, spawn_castles)
@@ -64,15 +113,106 @@ fn main() {
         .add_systems(Update, enemy_shoot)
         .add_systems(Update, update_enemy_info)
         .add_systems(Update, confine_player_movement)
-        .add_systems(Update, bullet_hits_castle)
-        .add_systems(Update, enemy_bullet_hits_player)
-        .add_systems(Update, enemy_bullet_hits_castle)
-        .add_systems(Update, update_lives)
+        .add_systems(Update, resolve_collisions.after(sweep_projectiles))
+        .add_systems(
+            Update,
+            kill_enemies
+                .after(resolve_collisions)
+                .before(handle_game_over)
+                .before(handle_wave_cleared),
+        )
+        .add_systems(Update, update_dying_enemies)
+        .add_systems(Update, spawn_effect_emitters.after(resolve_collisions))
+        .add_systems(Update, emit_particles)
+        .add_systems(Update, update_particles)
+        .add_systems(
+            Update,
+            trigger_feedback
+                .after(resolve_collisions)
+                .before(despawn_destroyed_castles)
+                .before(handle_game_over),
+        )
+        .add_systems(Update, shake_camera.after(trigger_feedback))
+        .add_systems(Update, apply_hit_stop.after(trigger_feedback))
+        .add_systems(Update, flash_damaged_sprites.after(animate_sprites))
+        .add_systems(Update, award_score.after(resolve_collisions))
+        .add_systems(Update, spawn_score_popups.after(award_score))
+        .add_systems(Update, update_score_popups)
+        .add_systems(Update, play_gameplay_sounds.after(resolve_collisions))
+        .add_systems(Update, record_game_stats.after(resolve_collisions))
+        .add_systems(Update, handle_player_hit.after(resolve_collisions))
+        .add_systems(Update, handle_castle_damaged.after(resolve_collisions))
+        .add_systems(Update, despawn_destroyed_castles.after(resolve_collisions))
+        .add_systems(Update, handle_projectile_destroyed.after(resolve_collisions))
+        .add_systems(Update, update_score_hud)
+        .add_systems(Update, update_wave_hud)
+        .add_systems(Update, update_lives_hud)
         .add_systems(Update, handle_game_start_music)
         .add_systems(Update, handle_game_over_music)
+        .add_systems(Update, play_sound_effects)
+        .add_systems(Update, play_music)
+        .add_systems(Update, update_music_intensity)
+        .add_systems(Update, crossfade_music)
         .add_systems(Update, handle_game_over)
-        .add_systems(Update, detect_game_won)
+        .add_systems(Update, detect_wave_cleared)
+        .add_systems(Update, handle_wave_cleared.after(detect_wave_cleared))
         .add_systems(Update, reset_lives)
+        .add_systems(Update, reset_score)
+        .add_systems(Update, reset_wave)
+        .add_systems(Update, main_menu_navigation)
+        .add_systems(Update, update_main_menu_items)
+        .add_systems(Update, translate_hud)
+        .add_systems(Update, return_to_title)
+        .add_systems(Update, enter_initials)
+        .add_systems(Update, update_initials_letters)
+        .add_systems(Update, toggle_pause)
+        .add_systems(Update, pause_on_focus_lost)
+        .add_systems(Update, apply_pause)
+        .add_systems(Update, pause_menu_navigation)
+        .add_systems(Update, update_pause_menu_items)
+        .add_systems(Update, options_navigation)
+        .add_systems(Update, update_options_items)
+        .add_systems(Update, apply_audio_settings)
+        .add_systems(Update, apply_display_settings)
+        .add_systems(Update, spawn_loading_screen)
+        .add_systems(Update, update_loading_screen)
         .add_systems(Update, game_loaded)
-        .run();
+        .add_systems(Update, start_countdown)
+        .add_systems(Update, update_countdown);
+
+    let missing_files = app.world.resource::<GameAssets>().missing_files();
+    if !missing_files.is_empty() {
+        for path in &missing_files {
+            error!("Missing asset: assets/{path}");
+        }
+        error!("{} asset(s) missing, not starting the game", missing_files.len());
+        if check_assets_mode {
+            for path in app.world.resource::<GameAssets>().unused_files() {
+                println!("unused  {path}");
+            }
+        }
+        std::process::exit(1);
+    }
+
+    if !check_assets_mode {
+        app.run();
+        return;
+    }
+
+    // Let `app.run()` return once `check_assets` is done, so the app is torn
+    // down before we exit with the result.
+    let failed = Arc::new(AtomicBool::new(false));
+    app.insert_resource(AssetCheck {
+        failed: failed.clone(),
+    })
+    .insert_resource(WinitSettings {
+        return_from_run: true,
+        ..default()
+    })
+    .add_systems(Update, check_assets);
+    app.run();
+
+    if failed.load(Ordering::Relaxed) {
+        std::process::exit(1);
+    }
 }
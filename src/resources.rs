@@ -1,6 +1,28 @@
+use bevy::asset::io::file::FileAssetReader;
+use bevy::asset::{Asset, RecursiveDependencyLoadState, UntypedHandle};
+use bevy::audio::{Decodable, Source};
 use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy::window::{PresentMode, WindowMode};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::Duration;
 
 pub const NUMBER_OF_LIVES: u32 = 4;
+pub const HIGH_SCORE_TABLE_SIZE: usize = 10;
+pub const INITIALS_LENGTH: usize = 3;
+pub const MAX_VOICES_PER_CLIP: usize = 4;
+pub const COUNTDOWN_SECONDS: f32 = 3.0;
+pub const MAX_PARTICLES: usize = 512;
+/// Kills less than this many seconds apart build up a combo.
+pub const COMBO_SECONDS: f32 = 1.0;
+/// Most bonus points a combo can add to a single kill.
+pub const MAX_COMBO_BONUS: u32 = 5;
+/// Kills worth at least this many points get a big, colored score popup.
+pub const BIG_POPUP_POINTS: u32 = 10;
 
 #[derive(Resource, PartialEq, Eq)]
 pub enum Game {
@@ -8,6 +30,8 @@ pub enum Game {
     LOADING,
     STARTED,
     ENDED,
+    INITIALS,
+    PAUSED,
 }
 
 impl Default for Game {
@@ -16,6 +40,36 @@ impl Default for Game {
     }
 }
 
+#[derive(Resource, Clone, Copy, PartialEq, Eq)]
+pub enum GameMode {
+    /// A single wave; clearing it wins the game.
+    Classic,
+    /// Waves keep coming until the player runs out of lives.
+    Endless,
+}
+
+impl Default for GameMode {
+    fn default() -> GameMode {
+        GameMode::Classic
+    }
+}
+
+impl GameMode {
+    pub fn label(&self) -> &'static str {
+        match self {
+            GameMode::Classic => "Classic",
+            GameMode::Endless => "Endless",
+        }
+    }
+
+    pub fn next(&self) -> GameMode {
+        match self {
+            GameMode::Classic => GameMode::Endless,
+            GameMode::Endless => GameMode::Classic,
+        }
+    }
+}
+
 #[derive(Resource)]
 pub struct Score {
     pub value: u32,
@@ -56,6 +110,459 @@ impl Default for HighScore {
     }
 }
 
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    English,
+    Dutch,
+}
+
+impl Language {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Language::English => "English",
+            Language::Dutch => "Nederlands",
+        }
+    }
+
+    pub fn next(&self) -> Language {
+        match self {
+            Language::English => Language::Dutch,
+            Language::Dutch => Language::English,
+        }
+    }
+
+    /// Looks up the translation of an English UI string, falling back to the
+    /// English text when there is none.
+    pub fn translate(&self, text: &'static str) -> &'static str {
+        if *self == Language::English {
+            return text;
+        }
+
+        match text {
+            "SAFE SPACE\nINVADERS" => "VEILIGE RUIMTE\nINDRINGERS",
+            "Start" => "Starten",
+            "Mode" => "Modus",
+            "High Scores" => "Topscores",
+            "Options" => "Opties",
+            "Credits" => "Makers",
+            "Quit" => "Afsluiten",
+            "Classic" => "Klassiek",
+            "Endless" => "Eindeloos",
+            "PAUSED" => "GEPAUZEERD",
+            "Resume" => "Verder",
+            "Restart" => "Opnieuw",
+            "Quit to title" => "Naar titelscherm",
+            "OPTIONS" => "OPTIES",
+            "Master volume" => "Hoofdvolume",
+            "Music volume" => "Muziekvolume",
+            "Effects volume" => "Effectenvolume",
+            "Voice volume" => "Stemvolume",
+            "Fullscreen" => "Volledig scherm",
+            "Vsync" => "Beeldsynchronisatie",
+            "Screen shake" => "Schermschudden",
+            "Hit-stop" => "Trefferpauze",
+            "Damage flash" => "Schadeflits",
+            "Starfield" => "Sterrenveld",
+            "Language" => "Taal",
+            "Back" => "Terug",
+            "On" => "Aan",
+            "Off" => "Uit",
+            "GO!" => "GA!",
+            "CREDITS" => "MAKERS",
+            "Made by" => "Gemaakt door",
+            "HIGH SCORES" => "TOPSCORES",
+            "No scores yet" => "Nog geen scores",
+            "SCORE" => "SCORE",
+            "HI" => "TOP",
+            "WAVE" => "GOLF",
+            "Loading..." => "Laden...",
+            "Failed to load:" => "Laden mislukt:",
+            "Score" => "Punten",
+            "NEW HIGH SCORE!" => "NIEUWE TOPSCORE!",
+            "Enter your initials" => "Vul je initialen in",
+            "Up/Down: letter  Left/Right: move  Enter: confirm" => {
+                "Op/Neer: letter  Links/Rechts: verplaats  Enter: bevestig"
+            }
+            _ => text,
+        }
+    }
+}
+
+/// User preferences, persisted in the user's config directory.
+#[derive(Resource, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct Settings {
+    pub master_volume: f32,
+    pub music_volume: f32,
+    pub sfx_volume: f32,
+    pub voice_volume: f32,
+    pub fullscreen: bool,
+    pub vsync: bool,
+    pub screen_shake: bool,
+    pub hit_stop: bool,
+    pub damage_flash: bool,
+    /// Scrolling stars instead of the static background image.
+    pub starfield: bool,
+    pub language: Language,
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings {
+            master_volume: 1.0,
+            music_volume: 0.8,
+            sfx_volume: 1.0,
+            voice_volume: 1.0,
+            fullscreen: false,
+            vsync: true,
+            screen_shake: true,
+            hit_stop: true,
+            damage_flash: true,
+            starfield: true,
+            language: Language::English,
+        }
+    }
+}
+
+impl Settings {
+    fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("safe-space-invaders").join("settings.ron"))
+    }
+
+    /// Reads the settings from disk, falling back to the defaults.
+    pub fn load() -> Settings {
+        let Some(path) = Settings::path() else {
+            return Settings::default();
+        };
+
+        match fs::read_to_string(&path) {
+            Ok(contents) => ron::from_str(&contents).unwrap_or_else(|error| {
+                warn!("Ignoring unreadable settings file {path:?}: {error}");
+                Settings::default()
+            }),
+            Err(_) => Settings::default(),
+        }
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let path = Settings::path().ok_or("no config directory available")?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|error| error.to_string())?;
+        }
+
+        let contents = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|error| error.to_string())?;
+        fs::write(path, contents).map_err(|error| error.to_string())
+    }
+
+    pub fn window_mode(&self) -> WindowMode {
+        match self.fullscreen {
+            true => WindowMode::BorderlessFullscreen,
+            false => WindowMode::Windowed,
+        }
+    }
+
+    pub fn present_mode(&self) -> PresentMode {
+        match self.vsync {
+            true => PresentMode::AutoVsync,
+            false => PresentMode::AutoNoVsync,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MusicTrack {
+    Menu,
+    Game,
+    Won,
+    Lost,
+}
+
+impl MusicTrack {
+    pub fn looping(&self) -> bool {
+        matches!(self, MusicTrack::Menu | MusicTrack::Game)
+    }
+}
+
+/// Which music track should be playing. Only one track plays at a time;
+/// switching crossfades from the old one to the new one.
+///
+/// `intensity` goes from 0 to 1 as the wave thins out or the invaders close in
+/// on the castles. It speeds up and swells the game music and fades in the
+/// `MarchBeat` layer on top of it.
+#[derive(Resource, Default)]
+pub struct MusicState {
+    pub current: Option<MusicTrack>,
+    pub intensity: f32,
+}
+
+/// The classic four note invader march, synthesized so it needs no audio
+/// file. Plays endlessly as a layer over the game music.
+#[derive(Asset, TypePath, Clone)]
+pub struct MarchBeat {
+    pub beats_per_second: f32,
+    /// Frequencies of the notes, played one per beat.
+    pub notes: [f32; 4],
+}
+
+impl Default for MarchBeat {
+    fn default() -> MarchBeat {
+        MarchBeat {
+            beats_per_second: 2.0,
+            notes: [98.0, 87.3, 77.8, 73.4],
+        }
+    }
+}
+
+impl Decodable for MarchBeat {
+    type DecoderItem = f32;
+    type Decoder = MarchBeatDecoder;
+
+    fn decoder(&self) -> MarchBeatDecoder {
+        MarchBeatDecoder {
+            beat: self.clone(),
+            sample: 0,
+        }
+    }
+}
+
+pub struct MarchBeatDecoder {
+    beat: MarchBeat,
+    sample: u64,
+}
+
+impl MarchBeatDecoder {
+    const SAMPLE_RATE: u32 = 44_100;
+    /// Part of every beat the note sounds for.
+    const NOTE_LENGTH: f32 = 0.35;
+}
+
+impl Iterator for MarchBeatDecoder {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let rate = MarchBeatDecoder::SAMPLE_RATE as f32;
+        let beat_samples = (rate / self.beat.beats_per_second) as u64;
+        let beat = (self.sample / beat_samples) as usize % self.beat.notes.len();
+        let progress = (self.sample % beat_samples) as f32 / beat_samples as f32;
+        let time = self.sample as f32 / rate;
+        self.sample += 1;
+
+        if progress > MarchBeatDecoder::NOTE_LENGTH {
+            return Some(0.0);
+        }
+        // A square wave with a short decay, like an old arcade cabinet.
+        let envelope = 1.0 - progress / MarchBeatDecoder::NOTE_LENGTH;
+        let phase = (time * self.beat.notes[beat]).fract();
+        let square = if phase < 0.5 { 1.0 } else { -1.0 };
+        Some(square * envelope * 0.3)
+    }
+}
+
+impl Source for MarchBeatDecoder {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        1
+    }
+
+    fn sample_rate(&self) -> u32 {
+        MarchBeatDecoder::SAMPLE_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+/// Particle entities that have expired and can be reused, so bursts don't
+/// spawn and despawn entities all the time. There are never more than
+/// `MAX_PARTICLES` particles in total.
+#[derive(Resource, Default)]
+pub struct ParticlePool {
+    pub free: Vec<Entity>,
+    pub total: usize,
+}
+
+/// Colors and density of the starfield. Every wave gets the next theme.
+pub struct StarfieldTheme {
+    pub star_color: Color,
+    pub density: f32,
+    pub nebula: Option<Color>,
+}
+
+impl StarfieldTheme {
+    pub fn for_wave(wave: u32) -> StarfieldTheme {
+        let themes = [
+            StarfieldTheme {
+                star_color: Color::WHITE,
+                density: 1.0,
+                nebula: None,
+            },
+            StarfieldTheme {
+                star_color: Color::rgb(0.75, 0.85, 1.0),
+                density: 1.3,
+                nebula: Some(Color::rgba(0.3, 0.2, 0.6, 0.12)),
+            },
+            StarfieldTheme {
+                star_color: Color::rgb(1.0, 0.85, 0.7),
+                density: 0.8,
+                nebula: Some(Color::rgba(0.7, 0.25, 0.2, 0.1)),
+            },
+            StarfieldTheme {
+                star_color: Color::rgb(0.8, 1.0, 0.85),
+                density: 1.6,
+                nebula: Some(Color::rgba(0.1, 0.5, 0.4, 0.1)),
+            },
+        ];
+
+        let index = wave.saturating_sub(1) as usize % themes.len();
+        themes.into_iter().nth(index).unwrap()
+    }
+}
+
+/// Real-time seconds left of the slow motion after the player is hit.
+#[derive(Resource, Default)]
+pub struct HitStop {
+    pub remaining: f32,
+}
+
+/// Running totals for the current game, logged when it ends.
+#[derive(Resource, Default)]
+pub struct GameStats {
+    pub shots_fired: u32,
+    pub enemies_killed: u32,
+    pub lives_lost: u32,
+    pub castles_lost: u32,
+}
+
+impl GameStats {
+    pub fn accuracy(&self) -> f32 {
+        match self.shots_fired {
+            0 => 0.0,
+            shots => self.enemies_killed as f32 / shots as f32 * 100.0,
+        }
+    }
+}
+
+/// Seconds left in the "3, 2, 1, GO" countdown. Enemies hold still and hold
+/// their fire while it runs.
+#[derive(Resource)]
+pub struct Countdown {
+    pub remaining: f32,
+}
+
+impl Default for Countdown {
+    fn default() -> Countdown {
+        Countdown { remaining: 0.0 }
+    }
+}
+
+impl Countdown {
+    pub fn is_running(&self) -> bool {
+        self.remaining > 0.0
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct HighScoreEntry {
+    pub initials: String,
+    pub score: u32,
+}
+
+/// Top scores, persisted in the user's data directory.
+#[derive(Resource, Serialize, Deserialize, Default)]
+pub struct HighScoreTable {
+    pub entries: Vec<HighScoreEntry>,
+}
+
+impl HighScoreTable {
+    fn path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("safe-space-invaders").join("highscores.ron"))
+    }
+
+    /// Reads the table from disk, starting empty if there is none yet.
+    pub fn load() -> HighScoreTable {
+        let Some(path) = HighScoreTable::path() else {
+            return HighScoreTable::default();
+        };
+
+        match fs::read_to_string(&path) {
+            Ok(contents) => ron::from_str(&contents).unwrap_or_else(|error| {
+                warn!("Ignoring unreadable high score file {path:?}: {error}");
+                HighScoreTable::default()
+            }),
+            Err(_) => HighScoreTable::default(),
+        }
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let path = HighScoreTable::path().ok_or("no data directory available")?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|error| error.to_string())?;
+        }
+
+        let contents = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|error| error.to_string())?;
+        fs::write(path, contents).map_err(|error| error.to_string())
+    }
+
+    pub fn best(&self) -> u32 {
+        self.entries.first().map_or(0, |entry| entry.score)
+    }
+
+    pub fn qualifies(&self, score: u32) -> bool {
+        score > 0
+            && (self.entries.len() < HIGH_SCORE_TABLE_SIZE
+                || self.entries.iter().any(|entry| score > entry.score))
+    }
+
+    pub fn insert(&mut self, initials: String, score: u32) {
+        // Ties keep the older entry on top.
+        let index = self
+            .entries
+            .iter()
+            .position(|entry| score > entry.score)
+            .unwrap_or(self.entries.len());
+        self.entries.insert(index, HighScoreEntry { initials, score });
+        self.entries.truncate(HIGH_SCORE_TABLE_SIZE);
+    }
+}
+
+/// Arcade-style initials being entered for a qualifying score.
+#[derive(Resource)]
+pub struct InitialsEntry {
+    pub letters: [char; INITIALS_LENGTH],
+    pub cursor: usize,
+    pub score: u32,
+    /// The language to show the high score table in afterwards.
+    pub language: Language,
+}
+
+impl InitialsEntry {
+    pub fn new(score: u32, language: Language) -> InitialsEntry {
+        InitialsEntry {
+            letters: ['A'; INITIALS_LENGTH],
+            cursor: 0,
+            score,
+            language,
+        }
+    }
+
+    pub fn cycle_letter(&mut self, forward: bool) {
+        let letter = self.letters[self.cursor] as u8 - b'A';
+        let step = if forward { 1 } else { 25 };
+        let next = (letter + step) % 26;
+        self.letters[self.cursor] = (b'A' + next) as char;
+    }
+
+    pub fn initials(&self) -> String {
+        self.letters.iter().collect()
+    }
+}
+
 #[derive(Resource)]
 pub struct Lives {
     pub value: u32,
@@ -69,6 +576,17 @@ impl Default for Lives {
     }
 }
 
+#[derive(Resource)]
+pub struct Wave {
+    pub value: u32,
+}
+
+impl Default for Wave {
+    fn default() -> Wave {
+        Wave { value: 1 }
+    }
+}
+
 #[derive(Debug)]
 pub enum EnemyStage {
     RIGHT,
@@ -79,42 +597,332 @@ pub enum EnemyStage {
 #[derive(Resource)]
 pub struct EnemyInfo {
     pub stage: EnemyStage,
+    pub alive: usize,
+    pub min_y: f32,
 }
 
 impl Default for EnemyInfo {
     fn default() -> EnemyInfo {
         EnemyInfo {
             stage: EnemyStage::RIGHT,
+            alive: 0,
+            min_y: f32::MAX,
         }
     }
 }
 
+/// One of the characters the enemy formation is made of.
+pub struct EnemyKind {
+    pub name: &'static str,
+    pub sprite: &'static str,
+    pub hit_sound: &'static str,
+}
+
 #[derive(Resource)]
 pub struct EnemyCatalog {
-    enemy_sprites: Vec<String>,
+    enemies: Vec<EnemyKind>,
 }
 
 impl Default for EnemyCatalog {
     fn default() -> EnemyCatalog {
         EnemyCatalog {
-            enemy_sprites: vec![
-                "daan".to_string(),
-                "erhan".to_string(),
-                "frank".to_string(),
-                "hus".to_string(),
-                "jeroen".to_string(),
-                "kas".to_string(),
-                "ryan".to_string(),
-                "storm".to_string(),
+            enemies: vec![
+                EnemyKind {
+                    name: "Daan",
+                    sprite: "sprites/daan.png",
+                    hit_sound: "audio/daanhit.ogg",
+                },
+                EnemyKind {
+                    name: "Erhan",
+                    sprite: "sprites/erhan.png",
+                    hit_sound: "audio/erhanhit.ogg",
+                },
+                EnemyKind {
+                    name: "Frank",
+                    sprite: "sprites/frank.png",
+                    hit_sound: "audio/Frankhit.ogg",
+                },
+                EnemyKind {
+                    name: "Hus",
+                    sprite: "sprites/hus.png",
+                    hit_sound: "audio/hushit.ogg",
+                },
+                EnemyKind {
+                    name: "Jeroen",
+                    sprite: "sprites/jeroen.png",
+                    hit_sound: "audio/jeroenhit.ogg",
+                },
+                EnemyKind {
+                    name: "Kas",
+                    sprite: "sprites/kas.png",
+                    hit_sound: "audio/Kashit.ogg",
+                },
+                EnemyKind {
+                    name: "Ryan",
+                    sprite: "sprites/ryan.png",
+                    hit_sound: "audio/Ryanhit.ogg",
+                },
+                EnemyKind {
+                    name: "Storm",
+                    sprite: "sprites/storm.png",
+                    hit_sound: "audio/stormhit.ogg",
+                },
             ],
         }
     }
 }
 
 impl EnemyCatalog {
-    pub fn get_random_enemy(&self) -> &str {
-        let idx = rand::random::<usize>() % self.enemy_sprites.len();
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.enemies.iter().map(|enemy| enemy.name)
+    }
+
+    /// Picks a random enemy, returning its index in the catalog.
+    pub fn get_random_enemy(&self) -> usize {
+        rand::random::<usize>() % self.enemies.len()
+    }
+}
 
-        &self.enemy_sprites[idx]
+/// Outcome of `--check-assets` mode. Shared with `main` so the exit code can
+/// be set after the app has shut down.
+#[derive(Resource)]
+pub struct AssetCheck {
+    pub failed: Arc<AtomicBool>,
+}
+
+/// Handles to every asset the game uses, loaded up front. This is the asset
+/// manifest: any file not loaded here is not shipped with the game.
+#[derive(Resource)]
+pub struct GameAssets {
+    pub font: Handle<Font>,
+    pub background: Handle<Image>,
+    pub game_won: Handle<Image>,
+    pub game_lost: Handle<Image>,
+    pub player: Handle<Image>,
+    pub bullet: Handle<Image>,
+    pub enemy_bullet: Handle<Image>,
+    pub castle: Handle<Image>,
+    /// Indexed by `Enemy::kind`.
+    pub enemy_sprites: Vec<Handle<Image>>,
+    /// Atlases cut from the images above by `build_sprite_sheets` once they
+    /// have loaded. Until then these handles point at nothing.
+    pub player_sheet: Handle<TextureAtlas>,
+    pub bullet_sheet: Handle<TextureAtlas>,
+    pub enemy_bullet_sheet: Handle<TextureAtlas>,
+    pub castle_sheet: Handle<TextureAtlas>,
+    /// Indexed by `Enemy::kind`.
+    pub enemy_sheets: Vec<Handle<TextureAtlas>>,
+    /// Indexed by `Enemy::kind`.
+    pub enemy_hit_sounds: Vec<Handle<AudioSource>>,
+    pub shot_sound: Handle<AudioSource>,
+    pub explode_sound: Handle<AudioSource>,
+    pub confirm_sound: Handle<AudioSource>,
+    pub menu_music: Handle<AudioSource>,
+    pub game_music: Handle<AudioSource>,
+    pub won_music: Handle<AudioSource>,
+    pub lost_music: Handle<AudioSource>,
+    /// The path behind every handle above.
+    pub manifest: Vec<(&'static str, UntypedHandle)>,
+}
+
+impl FromWorld for GameAssets {
+    fn from_world(world: &mut World) -> GameAssets {
+        let asset_server = world.resource::<AssetServer>();
+        let enemy_catalog = world.resource::<EnemyCatalog>();
+        let atlases = world.resource::<Assets<TextureAtlas>>().get_handle_provider();
+        let mut manifest = Vec::new();
+
+        fn load<A: Asset>(
+            asset_server: &AssetServer,
+            manifest: &mut Vec<(&'static str, UntypedHandle)>,
+            path: &'static str,
+        ) -> Handle<A> {
+            let handle: Handle<A> = asset_server.load(path);
+            manifest.push((path, handle.clone().untyped()));
+            handle
+        }
+
+        GameAssets {
+            font: load(asset_server, &mut manifest, "fonts/Sanspix-Regular.ttf"),
+            background: load(asset_server, &mut manifest, "images/background.png"),
+            game_won: load(asset_server, &mut manifest, "images/game-won.png"),
+            game_lost: load(asset_server, &mut manifest, "images/game-lost.png"),
+            player: load(asset_server, &mut manifest, "sprites/spaceship.png"),
+            bullet: load(asset_server, &mut manifest, "sprites/bullet.png"),
+            enemy_bullet: load(asset_server, &mut manifest, "sprites/bitterbal.png"),
+            castle: load(asset_server, &mut manifest, "sprites/castle.png"),
+            enemy_sprites: enemy_catalog
+                .enemies
+                .iter()
+                .map(|enemy| load(asset_server, &mut manifest, enemy.sprite))
+                .collect(),
+            player_sheet: atlases.reserve_handle().typed(),
+            bullet_sheet: atlases.reserve_handle().typed(),
+            enemy_bullet_sheet: atlases.reserve_handle().typed(),
+            castle_sheet: atlases.reserve_handle().typed(),
+            enemy_sheets: enemy_catalog
+                .enemies
+                .iter()
+                .map(|_| atlases.reserve_handle().typed())
+                .collect(),
+            enemy_hit_sounds: enemy_catalog
+                .enemies
+                .iter()
+                .map(|enemy| load(asset_server, &mut manifest, enemy.hit_sound))
+                .collect(),
+            shot_sound: load(asset_server, &mut manifest, "audio/schieten.ogg"),
+            explode_sound: load(asset_server, &mut manifest, "audio/explode.ogg"),
+            confirm_sound: load(asset_server, &mut manifest, "audio/start_effect.ogg"),
+            menu_music: load(asset_server, &mut manifest, "audio/menu-music-loop.ogg"),
+            game_music: load(asset_server, &mut manifest, "audio/game-start-music.ogg"),
+            won_music: load(asset_server, &mut manifest, "audio/win-sound.ogg"),
+            lost_music: load(asset_server, &mut manifest, "audio/game-over.ogg"),
+            manifest,
+        }
+    }
+}
+
+impl GameAssets {
+    pub fn music(&self, track: MusicTrack) -> Handle<AudioSource> {
+        match track {
+            MusicTrack::Menu => self.menu_music.clone(),
+            MusicTrack::Game => self.game_music.clone(),
+            MusicTrack::Won => self.won_music.clone(),
+            MusicTrack::Lost => self.lost_music.clone(),
+        }
+    }
+
+    /// Each sprite sheet paired with the image it is cut from.
+    pub fn sprite_sheets(&self) -> impl Iterator<Item = (&Handle<Image>, &Handle<TextureAtlas>)> {
+        [
+            (&self.player, &self.player_sheet),
+            (&self.bullet, &self.bullet_sheet),
+            (&self.enemy_bullet, &self.enemy_bullet_sheet),
+            (&self.castle, &self.castle_sheet),
+        ]
+        .into_iter()
+        .chain(self.enemy_sprites.iter().zip(&self.enemy_sheets))
+    }
+
+    /// How many manifest entries have finished loading, including their
+    /// dependencies, and which ones failed.
+    pub fn load_progress(&self, asset_server: &AssetServer) -> LoadProgress {
+        let mut progress = LoadProgress {
+            loaded: 0,
+            total: self.manifest.len(),
+            failed: Vec::new(),
+        };
+
+        for (path, handle) in &self.manifest {
+            match asset_server.get_recursive_dependency_load_state(handle.id()) {
+                Some(RecursiveDependencyLoadState::Loaded) => progress.loaded += 1,
+                Some(RecursiveDependencyLoadState::Failed) => progress.failed.push(*path),
+                _ => (),
+            }
+        }
+
+        progress
+    }
+
+    fn assets_dir() -> PathBuf {
+        FileAssetReader::get_base_path().join("assets")
+    }
+
+    /// Manifest entries without a file on disk. The comparison is case
+    /// sensitive, like the filesystems we ship to.
+    pub fn missing_files(&self) -> Vec<&'static str> {
+        let assets_dir = GameAssets::assets_dir();
+        let present = files_in(&assets_dir, &assets_dir);
+
+        self.manifest
+            .iter()
+            .map(|(path, _)| *path)
+            .filter(|path| !present.iter().any(|file| file == path))
+            .collect()
+    }
+
+    /// Files in the assets directory that nothing in the manifest refers to.
+    pub fn unused_files(&self) -> Vec<String> {
+        let assets_dir = GameAssets::assets_dir();
+
+        files_in(&assets_dir, &assets_dir)
+            .into_iter()
+            .filter(|file| !self.manifest.iter().any(|(path, _)| path == file))
+            .filter(|file| file != ColliderOverrides::FILE)
+            .collect()
+    }
+}
+
+/// A hand-made hitbox for a sprite, in pixels.
+#[derive(Serialize, Deserialize, Clone, Copy)]
+pub enum ColliderShape {
+    Cuboid { width: f32, height: f32 },
+    Ball { radius: f32 },
+}
+
+/// Per-sprite hitboxes from `assets/colliders.ron`, keyed by the sprite's
+/// asset path. Sprites that aren't listed get a collider fitted to their
+/// opaque pixels.
+#[derive(Resource, Deserialize, Default)]
+pub struct ColliderOverrides {
+    pub shapes: HashMap<String, ColliderShape>,
+}
+
+impl ColliderOverrides {
+    pub const FILE: &'static str = "colliders.ron";
+
+    /// Reads the overrides, if there are any.
+    pub fn load() -> ColliderOverrides {
+        let path = GameAssets::assets_dir().join(ColliderOverrides::FILE);
+
+        match fs::read_to_string(&path) {
+            Ok(contents) => ron::from_str(&contents).unwrap_or_else(|error| {
+                warn!("Ignoring unreadable collider overrides {path:?}: {error}");
+                ColliderOverrides::default()
+            }),
+            Err(_) => ColliderOverrides::default(),
+        }
+    }
+}
+
+pub struct LoadProgress {
+    pub loaded: usize,
+    pub total: usize,
+    pub failed: Vec<&'static str>,
+}
+
+impl LoadProgress {
+    pub fn is_done(&self) -> bool {
+        self.loaded == self.total
+    }
+
+    pub fn fraction(&self) -> f32 {
+        match self.total {
+            0 => 1.0,
+            total => self.loaded as f32 / total as f32,
+        }
+    }
+}
+
+/// Lists every file below `dir`, as `/`-separated paths relative to `root`.
+fn files_in(root: &Path, dir: &Path) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut files = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            files.extend(files_in(root, &path));
+        } else if let Ok(relative) = path.strip_prefix(root) {
+            let components: Vec<_> = relative
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect();
+            files.push(components.join("/"));
+        }
     }
+    files.sort();
+    files
 }
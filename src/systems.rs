@@ -2,65 +2,394 @@ use crate::components::*;
 use crate::events::*;
 use crate::resources::*;
 use std::string::ToString;
+use std::sync::atomic::Ordering;
 
 use bevy::prelude::*;
-use bevy::window::PrimaryWindow;
-
-use bevy::audio::{PlaybackMode, Volume};
-use bevy::math::vec3;
+use bevy::app::AppExit;
+use bevy::asset::LoadState;
+use bevy::render::camera::{ScalingMode, Viewport};
+use bevy::render::render_resource::TextureFormat;
+use bevy::utils::{HashMap, HashSet};
+use bevy::window::{PrimaryWindow, WindowFocused};
+
+use bevy::audio::{PlaybackMode, SpatialScale, Volume};
+use bevy::math::{vec2, vec3};
 use bevy::{prelude::*, render::render_resource::PrimitiveTopology, sprite::MaterialMesh2dBundle};
 
 use bevy_xpbd_2d::{math::*, prelude::*};
-use rand::{random, Rng};
+use rand::Rng;
 
 pub const PLAYER_SPEED: f32 = 500.0;
 pub const PLAYER_SIZE: f32 = 64.0;
+pub const BULLET_SPEED: f32 = 500.0;
+pub const ENEMY_BULLET_SPEED: f32 = 200.0;
 // Player sprite size.
-pub const CASTLE_HEIGHT: f32 = 70.0;
-pub const CASTLE_WIDTH: f32 = 30.0;
 pub const NUMBER_OF_CASTLES: u32 = 4;
 
-pub const WINDOW_WIDTH: f32 = 600.0;
-pub const WINDOW_HEIGHT: f32 = 800.0;
+/// Size of the logical playfield. The camera scales it to fit the window.
+pub const PLAYFIELD_WIDTH: f32 = 600.0;
+pub const PLAYFIELD_HEIGHT: f32 = 800.0;
 
 const AMOUNT_OF_ROWS: u32 = 5;
 const AMOUNT_OF_ENEMIES: u32 = 10;
 const ENEMY_SIZE: f32 = 32.0;
 
-const GET_HIT_SOUNDS: [&str; 8] = [
-    "audio/daanhit.ogg",
-    "audio/erhanhit.ogg",
-    "audio/Frankhit.ogg",
-    "audio/hushit.ogg",
-    "audio/jeroenhit.ogg",
-    "audio/kashit.ogg",
-    "audio/Ryanhit.ogg",
-    "audio/stormhit.ogg",
-];
-
 pub fn spawn_game_intro(
     mut commands: Commands,
-    window_query: Query<&Window, With<PrimaryWindow>>,
-    asset_server: Res<AssetServer>,
+    game_assets: Res<GameAssets>,
+    game_mode: Res<GameMode>,
+    settings: Res<Settings>,
 ) {
-    let intro_asset_filename = "images/intro.png";
-    let window: &Window = window_query.get_single().unwrap();
+    spawn_main_menu(&mut commands, &game_assets, *game_mode, settings.language);
+}
+
+pub fn spawn_main_menu(
+    commands: &mut Commands,
+    game_assets: &GameAssets,
+    game_mode: GameMode,
+    language: Language,
+) {
+    let text_style = TextStyle {
+        font: game_assets.font.clone(),
+        font_size: 32.0,
+        ..default()
+    };
+
+    commands
+        .spawn((
+            ImageBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(12.0),
+                    ..default()
+                },
+                image: game_assets.background.clone().into(),
+                ..default()
+            },
+            MainMenu { selected: 0 },
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    language.translate("SAFE SPACE\nINVADERS"),
+                    TextStyle {
+                        font_size: 64.0,
+                        color: Color::ORANGE,
+                        ..text_style.clone()
+                    },
+                )
+                .with_text_alignment(TextAlignment::Center)
+                .with_style(Style {
+                    margin: UiRect::bottom(Val::Px(40.0)),
+                    ..default()
+                }),
+                MainMenuTitle,
+            ));
+
+            for action in MenuAction::ALL {
+                parent.spawn((
+                    TextBundle::from_section(
+                        menu_item_label(action, game_mode, language),
+                        text_style.clone(),
+                    )
+                    .with_style(Style {
+                        padding: UiRect::axes(Val::Px(16.0), Val::Px(4.0)),
+                        ..default()
+                    }),
+                    MainMenuItem { action },
+                ));
+            }
+        });
+}
+
+fn menu_item_label(action: MenuAction, game_mode: GameMode, language: Language) -> String {
+    let label = match action {
+        MenuAction::Start => "Start",
+        MenuAction::Mode => "Mode",
+        MenuAction::HighScores => "High Scores",
+        MenuAction::Options => "Options",
+        MenuAction::Credits => "Credits",
+        MenuAction::Quit => "Quit",
+    };
+
+    match action {
+        MenuAction::Mode => format!(
+            "{}: {}",
+            language.translate(label),
+            language.translate(game_mode.label())
+        ),
+        _ => language.translate(label).to_string(),
+    }
+}
+
+pub fn play_menu_sound(commands: &mut Commands, game_assets: &GameAssets, confirm: bool) {
+    let (menu_sound, volume) = match confirm {
+        true => (&game_assets.confirm_sound, 0.6),
+        false => (&game_assets.shot_sound, 0.3),
+    };
+    play_sound(commands, menu_sound, AudioBus::Sfx, volume, 0.0, None);
+}
 
+/// Queues a sound effect. `pitch_variation` is the largest random change in
+/// playback speed, so repeated sounds don't all sound the same. Sounds with a
+/// `position` are panned towards the side of the screen they come from.
+pub fn play_sound(
+    commands: &mut Commands,
+    source: &Handle<AudioSource>,
+    bus: AudioBus,
+    volume: f32,
+    pitch_variation: f32,
+    position: Option<Vec3>,
+) {
     commands.spawn((
-        SpriteBundle {
-            transform: Transform::from_xyz(window.width() / 2.0, window.height() / 2.0, 0.0),
-            texture: asset_server.load(intro_asset_filename),
-            ..default()
+        SoundEffect {
+            source: source.clone(),
+            volume,
+            pitch_variation,
+            position,
         },
-        IntroScreen {},
+        bus,
     ));
 }
 
+/// Starts queued sound effects, allowing at most `MAX_VOICES_PER_CLIP`
+/// instances of the same clip at once. Sound effects steal the oldest voice,
+/// voice lines are dropped instead so nobody gets cut off mid-sentence.
+pub fn play_sound_effects(
+    mut commands: Commands,
+    time: Res<Time<Real>>,
+    settings: Res<Settings>,
+    queued_query: Query<(Entity, &SoundEffect, &AudioBus)>,
+    playing_query: Query<(Entity, &PlayingSound)>,
+) {
+    let mut playing: Vec<(Entity, AssetId<AudioSource>, f32)> = playing_query
+        .iter()
+        .map(|(entity, sound)| (entity, sound.clip, sound.started))
+        .collect();
+
+    for (entity, sound_effect, bus) in queued_query.iter() {
+        let clip = sound_effect.source.id();
+        let voices = playing.iter().filter(|(_, id, _)| *id == clip).count();
+        if voices >= MAX_VOICES_PER_CLIP {
+            if *bus == AudioBus::Voice {
+                commands.entity(entity).despawn();
+                continue;
+            }
+
+            let oldest = playing
+                .iter()
+                .enumerate()
+                .filter(|(_, (_, id, _))| *id == clip)
+                .min_by(|(_, a), (_, b)| a.2.total_cmp(&b.2))
+                .map(|(index, _)| index);
+            if let Some(index) = oldest {
+                let (oldest_entity, _, _) = playing.swap_remove(index);
+                commands.entity(oldest_entity).despawn();
+            }
+        }
+
+        let speed = 1.0 + (rand::random::<f32>() * 2.0 - 1.0) * sound_effect.pitch_variation;
+        let mut sound = commands.entity(entity);
+        sound.remove::<SoundEffect>().insert((
+            AudioBundle {
+                source: sound_effect.source.clone(),
+                settings: PlaybackSettings::DESPAWN
+                    .with_volume(Volume::new_relative(
+                        sound_effect.volume * bus.volume(&settings),
+                    ))
+                    .with_speed(speed)
+                    .with_spatial(sound_effect.position.is_some()),
+            },
+            PlayingSound {
+                clip,
+                started: time.elapsed_seconds(),
+                volume: sound_effect.volume,
+            },
+        ));
+        if let Some(position) = sound_effect.position {
+            sound.insert(TransformBundle::from_transform(Transform::from_translation(
+                position,
+            )));
+        }
+        playing.push((entity, clip, time.elapsed_seconds()));
+    }
+}
+
+pub fn main_menu_navigation(
+    mut commands: Commands,
+    mut keyboard_input: ResMut<Input<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    game_assets: Res<GameAssets>,
+    mut main_menu_query: Query<(Entity, &mut MainMenu)>,
+    overlay_query: Query<Entity, Or<(With<HighScoreTableScreen>, With<CreditsScreen>)>>,
+    options_query: Query<(), With<OptionsScreen>>,
+    high_score_table: Res<HighScoreTable>,
+    enemy_catalog: Res<EnemyCatalog>,
+    settings: Res<Settings>,
+    mut game_mode: ResMut<GameMode>,
+    mut app_exit_event_writer: EventWriter<AppExit>,
+    mut start_game_event_writer: EventWriter<GameStartRequested>,
+    mut game: ResMut<Game>,
+) {
+    if *game != Game::INTRO || !options_query.is_empty() {
+        return;
+    }
+    let Ok((menu_entity, mut main_menu)) = main_menu_query.get_single_mut() else {
+        return;
+    };
+
+    let confirmed = keyboard_input.any_just_pressed([KeyCode::Return, KeyCode::Space])
+        || gamepad_just_pressed(&gamepads, &gamepad_buttons, GamepadButtonType::South);
+    let back = keyboard_input.any_just_pressed([KeyCode::Escape, KeyCode::Back])
+        || gamepad_just_pressed(&gamepads, &gamepad_buttons, GamepadButtonType::East);
+
+    // An open sub-screen swallows input until it is closed.
+    if !overlay_query.is_empty() {
+        if confirmed || back {
+            for overlay in overlay_query.iter() {
+                commands.entity(overlay).despawn_recursive();
+            }
+            play_menu_sound(&mut commands, &game_assets, false);
+        }
+        return;
+    }
+
+    let count = MenuAction::ALL.len();
+    if keyboard_input.any_just_pressed([KeyCode::Up, KeyCode::W])
+        || gamepad_just_pressed(&gamepads, &gamepad_buttons, GamepadButtonType::DPadUp)
+    {
+        main_menu.selected = (main_menu.selected + count - 1) % count;
+        play_menu_sound(&mut commands, &game_assets, false);
+    }
+    if keyboard_input.any_just_pressed([KeyCode::Down, KeyCode::S])
+        || gamepad_just_pressed(&gamepads, &gamepad_buttons, GamepadButtonType::DPadDown)
+    {
+        main_menu.selected = (main_menu.selected + 1) % count;
+        play_menu_sound(&mut commands, &game_assets, false);
+    }
+
+    if !confirmed {
+        return;
+    }
+    // Don't let the same key press fire a bullet.
+    keyboard_input.clear_just_pressed(KeyCode::Space);
+    play_menu_sound(&mut commands, &game_assets, true);
+
+    match MenuAction::ALL[main_menu.selected] {
+        MenuAction::Start => {
+            commands.entity(menu_entity).despawn_recursive();
+            start_game_event_writer.send(GameStartRequested {});
+            *game = Game::LOADING;
+        }
+        MenuAction::Mode => *game_mode = game_mode.next(),
+        MenuAction::HighScores => {
+            spawn_high_score_table(&mut commands, &game_assets, &high_score_table, settings.language)
+        }
+        MenuAction::Options => spawn_options_screen(&mut commands, &game_assets, &settings),
+        MenuAction::Credits => {
+            spawn_credits(&mut commands, &game_assets, &enemy_catalog, settings.language)
+        }
+        MenuAction::Quit => app_exit_event_writer.send(AppExit),
+    }
+}
+
+pub fn update_main_menu_items(
+    main_menu_query: Query<Ref<MainMenu>>,
+    mut item_query: Query<(&mut Text, &mut BackgroundColor, &MainMenuItem)>,
+    mut title_query: Query<&mut Text, (With<MainMenuTitle>, Without<MainMenuItem>)>,
+    game_mode: Res<GameMode>,
+    settings: Res<Settings>,
+) {
+    let Ok(main_menu) = main_menu_query.get_single() else {
+        return;
+    };
+    if !main_menu.is_changed() && !game_mode.is_changed() && !settings.is_changed() {
+        return;
+    }
+
+    for mut text in &mut title_query {
+        text.sections[0].value = settings.language.translate("SAFE SPACE\nINVADERS").to_string();
+    }
+
+    let selected = MenuAction::ALL[main_menu.selected];
+    for (mut text, mut background_color, item) in &mut item_query {
+        let focused = item.action == selected;
+        text.sections[0].value = menu_item_label(item.action, *game_mode, settings.language);
+        text.sections[0].style.color = if focused { Color::YELLOW } else { Color::WHITE };
+        *background_color = if focused {
+            Color::rgba(1.0, 1.0, 1.0, 0.15).into()
+        } else {
+            Color::NONE.into()
+        };
+    }
+}
+
+pub fn spawn_credits(
+    commands: &mut Commands,
+    game_assets: &GameAssets,
+    enemy_catalog: &EnemyCatalog,
+    language: Language,
+) {
+    let text_style = TextStyle {
+        font: game_assets.font.clone(),
+        font_size: 30.0,
+        ..default()
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(8.0),
+                    ..default()
+                },
+                background_color: Color::rgba(0.0, 0.0, 0.0, 0.85).into(),
+                z_index: ZIndex::Global(10),
+                ..default()
+            },
+            CreditsScreen,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                language.translate("CREDITS"),
+                TextStyle {
+                    font_size: 40.0,
+                    color: Color::YELLOW,
+                    ..text_style.clone()
+                },
+            ));
+            parent.spawn(TextBundle::from_section(
+                language.translate("Made by"),
+                TextStyle {
+                    font_size: 20.0,
+                    ..text_style.clone()
+                },
+            ));
+
+            for name in enemy_catalog.names() {
+                parent.spawn(TextBundle::from_section(name, text_style.clone()));
+            }
+        });
+}
+
 pub fn spawn_game_background(
     mut commands: Commands,
-    window_query: Query<&Window, With<PrimaryWindow>>,
     mut game_over_screen_query: Query<Entity, With<GameOverScreen>>,
-    asset_server: Res<AssetServer>,
+    background_query: Query<Entity, With<GameScreen>>,
+    game_assets: Res<GameAssets>,
+    settings: Res<Settings>,
     mut game_start_event_reader: EventReader<GameStartRequested>,
 ) {
     match game_start_event_reader.read().next() {
@@ -70,18 +399,25 @@ pub fn spawn_game_background(
                 commands.entity(screen).despawn();
             }
 
+            // Replace the background of the previous game.
+            for background in background_query.iter() {
+                commands.entity(background).despawn();
+            }
+
             // Spawn new screen.
-            let intro_asset_filename = "images/background.png";
-            let window: &Window = window_query.get_single().unwrap();
+            if settings.starfield {
+                spawn_starfield(&mut commands, &StarfieldTheme::for_wave(1));
+                return;
+            }
 
             commands.spawn((
                 SpriteBundle {
                     transform: Transform::from_xyz(
-                        window.width() / 2.0,
-                        window.height() / 2.0,
+                        PLAYFIELD_WIDTH / 2.0,
+                        PLAYFIELD_HEIGHT / 2.0,
                         -10.0,
                     ),
-                    texture: asset_server.load(intro_asset_filename),
+                    texture: game_assets.background.clone(),
                     ..default()
                 },
                 GameScreen {},
@@ -91,141 +427,368 @@ pub fn spawn_game_background(
     }
 }
 
+/// Fills the screen with three layers of stars, the nearer ones bigger,
+/// brighter and faster, over an optional nebula.
+pub fn spawn_starfield(commands: &mut Commands, theme: &StarfieldTheme) {
+    // (stars per 100x100 pixels, size, speed, brightness)
+    const LAYERS: [(f32, f32, f32, f32); 3] = [
+        (0.8, 1.0, 15.0, 0.4),
+        (0.4, 2.0, 40.0, 0.7),
+        (0.15, 3.0, 90.0, 1.0),
+    ];
+    const NEBULA_CLOUDS: usize = 5;
+
+    let mut rng = rand::thread_rng();
+    let area = PLAYFIELD_WIDTH * PLAYFIELD_HEIGHT / 10_000.0;
+
+    let mut spawn_star = |commands: &mut Commands, size: f32, color: Color, speed: f32, z: f32| {
+        let x = rng.gen_range(0.0..PLAYFIELD_WIDTH);
+        let y = rng.gen_range(0.0..PLAYFIELD_HEIGHT);
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color,
+                    custom_size: Some(Vec2::splat(size)),
+                    ..default()
+                },
+                transform: Transform::from_xyz(x, y, z),
+                ..default()
+            },
+            Star { speed },
+            GameScreen {},
+        ));
+    };
+
+    if let Some(nebula) = theme.nebula {
+        for _ in 0..NEBULA_CLOUDS {
+            let size = PLAYFIELD_WIDTH * 0.6;
+            spawn_star(commands, size, nebula, 6.0, -9.5);
+        }
+    }
+
+    let [r, g, b, _] = theme.star_color.as_rgba_f32();
+    for (density, size, speed, brightness) in LAYERS {
+        let count = (density * theme.density * area) as usize;
+        let color = Color::rgb(r * brightness, g * brightness, b * brightness);
+        for _ in 0..count {
+            spawn_star(commands, size, color, speed, -9.0);
+        }
+    }
+}
+
+pub fn scroll_starfield(
+    time: Res<Time>,
+    mut star_query: Query<(&Star, &mut Transform, &Sprite)>,
+) {
+    for (star, mut transform, sprite) in &mut star_query {
+        transform.translation.y -= star.speed * time.delta_seconds();
+
+        // Wrap around once the star is fully below the screen.
+        let half_size = sprite.custom_size.map_or(0.0, |size| size.y / 2.0);
+        if transform.translation.y < -half_size {
+            transform.translation.y += PLAYFIELD_HEIGHT + 2.0 * half_size;
+        }
+    }
+}
+
+/// Gives every new wave its own colors and density.
+pub fn change_starfield_theme(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    star_query: Query<Entity, With<Star>>,
+    mut wave_started_event_reader: EventReader<WaveStarted>,
+) {
+    let Some(event) = wave_started_event_reader.read().last() else {
+        return;
+    };
+    if !settings.starfield || star_query.is_empty() {
+        return;
+    }
+
+    for star in star_query.iter() {
+        commands.entity(star).despawn();
+    }
+    spawn_starfield(&mut commands, &StarfieldTheme::for_wave(event.wave));
+}
+
 pub fn start_game(
     mut commands: Commands,
     keyboard_input: Res<Input<KeyCode>>,
     time: Res<Time>,
     mut start_game_event_writer: EventWriter<GameStartRequested>,
-    mut intro_query: Query<(Entity, &Transform), With<IntroScreen>>,
+    high_score_table_query: Query<Entity, With<HighScoreTableScreen>>,
     mut game: ResMut<Game>,
 ) {
-    if keyboard_input.just_pressed(KeyCode::Return)
-        && (*game == Game::INTRO || *game == Game::ENDED)
-    {
+    if keyboard_input.just_pressed(KeyCode::Return) && *game == Game::ENDED {
         start_game_event_writer.send(GameStartRequested {});
-        if let Ok((intro_entity, intro_transform)) = intro_query.get_single_mut() {
-            commands.entity(intro_entity).despawn();
+        for table in high_score_table_query.iter() {
+            commands.entity(table).despawn_recursive();
         }
         *game = Game::LOADING;
     }
 }
 
-pub fn start_menu_music(mut commands: Commands, asset_server: Res<AssetServer>) {
-    let menu_music_filename = "audio/menu-music-loop.ogg";
-    commands.spawn((
-        AudioBundle {
-            source: asset_server.load(menu_music_filename),
-            settings: PlaybackSettings::LOOP,
-            ..default()
-        },
-        MenuMusic {},
-    ));
+pub fn return_to_title(
+    mut commands: Commands,
+    keyboard_input: Res<Input<KeyCode>>,
+    game_assets: Res<GameAssets>,
+    game_over_query: Query<
+        Entity,
+        Or<(
+            With<GameOverScreen>,
+            With<ScoreText>,
+            With<HighScoreTableScreen>,
+            With<GameScreen>,
+        )>,
+    >,
+    mut hud_query: Query<&mut Visibility, With<Hud>>,
+    game_mode: Res<GameMode>,
+    settings: Res<Settings>,
+    mut play_music_event_writer: EventWriter<PlayMusic>,
+    mut game: ResMut<Game>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::Escape) || *game != Game::ENDED {
+        return;
+    }
+
+    for entity in game_over_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    for mut visibility in &mut hud_query {
+        *visibility = Visibility::Hidden;
+    }
+    spawn_main_menu(&mut commands, &game_assets, *game_mode, settings.language);
+    play_music_event_writer.send(PlayMusic {
+        track: MusicTrack::Menu,
+    });
+    *game = Game::INTRO;
+}
+
+pub fn spawn_high_score_table(
+    commands: &mut Commands,
+    game_assets: &GameAssets,
+    high_score_table: &HighScoreTable,
+    language: Language,
+) {
+    let text_style = TextStyle {
+        font: game_assets.font.clone(),
+        font_size: 30.0,
+        ..default()
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(8.0),
+                    ..default()
+                },
+                background_color: Color::rgba(0.0, 0.0, 0.0, 0.85).into(),
+                z_index: ZIndex::Global(10),
+                ..default()
+            },
+            HighScoreTableScreen,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                language.translate("HIGH SCORES"),
+                TextStyle {
+                    font_size: 40.0,
+                    color: Color::YELLOW,
+                    ..text_style.clone()
+                },
+            ));
+
+            if high_score_table.entries.is_empty() {
+                parent.spawn(TextBundle::from_section(
+                    language.translate("No scores yet"),
+                    text_style.clone(),
+                ));
+            }
+
+            for (rank, entry) in high_score_table.entries.iter().enumerate() {
+                parent.spawn(TextBundle::from_section(
+                    format!("{:>2}. {} {:>6}", rank + 1, entry.initials, entry.score),
+                    text_style.clone(),
+                ));
+            }
+        });
+}
+
+pub fn start_menu_music(mut play_music_event_writer: EventWriter<PlayMusic>) {
+    play_music_event_writer.send(PlayMusic {
+        track: MusicTrack::Menu,
+    });
 }
 
 pub fn handle_game_start_music(
     mut commands: Commands,
-    asset_server: Res<AssetServer>,
-    mut menu_music_query: Query<Entity, With<MenuMusic>>,
-    mut game_over_music_query: Query<Entity, With<GameOverMusic>>,
     mut score_text_query: Query<Entity, With<ScoreText>>,
     mut game_start_event_reader: EventReader<GameStartRequested>,
+    mut play_music_event_writer: EventWriter<PlayMusic>,
 ) {
     match game_start_event_reader.read().next() {
         Some(_) => {
-            // Stop other music.
-            if let Ok(music) = menu_music_query.get_single_mut() {
-                commands.entity(music).despawn();
-            }
-            if let Ok(music) = game_over_music_query.get_single_mut() {
-                commands.entity(music).despawn();
-            }
             if let Ok(text) = score_text_query.get_single_mut() {
                 commands.entity(text).despawn();
             }
 
-            // Start game over music.
-            let game_start_effect_filename = "audio/game-start-music.ogg";
-            commands.spawn((
-                AudioBundle {
-                    source: asset_server.load(game_start_effect_filename),
-                    settings: PlaybackSettings::LOOP,
-                    ..default()
-                },
-                GameStartMusic {},
-            ));
-            let game_start_filename = "audio/game-start-music.ogg";
-            commands.spawn((
-                AudioBundle {
-                    source: asset_server.load(game_start_filename),
-                    settings: PlaybackSettings::LOOP,
-                    ..default()
-                },
-                GameStartMusic {},
-            ));
+            play_music_event_writer.send(PlayMusic {
+                track: MusicTrack::Game,
+            });
         }
         None => (),
     }
 }
 
 pub fn handle_game_over_music(
-    mut commands: Commands,
-    asset_server: Res<AssetServer>,
-    mut menu_music_query: Query<Entity, With<MenuMusic>>,
-    mut game_start_music_query: Query<Entity, With<GameStartMusic>>,
     mut game_over_event_reader: EventReader<GameOver>,
+    mut play_music_event_writer: EventWriter<PlayMusic>,
 ) {
     match game_over_event_reader.read().next() {
         Some(game_over_event) => {
-            // Stop other music.
-            for music in menu_music_query.iter_mut() {
-                commands.entity(music).despawn();
-            }
-            for music in game_start_music_query.iter_mut() {
-                commands.entity(music).despawn();
+            let track = match game_over_event.won {
+                true => MusicTrack::Won,
+                false => MusicTrack::Lost,
+            };
+            play_music_event_writer.send(PlayMusic { track });
+        }
+        None => (),
+    }
+}
+
+/// Switches to the requested track, fading out whatever played before.
+pub fn play_music(
+    mut commands: Commands,
+    game_assets: Res<GameAssets>,
+    mut march_beats: ResMut<Assets<MarchBeat>>,
+    mut march_beat: Local<Option<Handle<MarchBeat>>>,
+    mut music_state: ResMut<MusicState>,
+    mut music_query: Query<&mut Music>,
+    mut play_music_event_reader: EventReader<PlayMusic>,
+) {
+    let Some(event) = play_music_event_reader.read().last() else {
+        return;
+    };
+
+    let already_playing = music_query
+        .iter()
+        .any(|music| music.track == event.track && !music.fading_out);
+    if music_state.current == Some(event.track) && already_playing {
+        return;
+    }
+
+    for mut music in &mut music_query {
+        music.fading_out = true;
+    }
+
+    let playback_settings = match event.track.looping() {
+        true => PlaybackSettings::LOOP,
+        false => PlaybackSettings::DESPAWN,
+    };
+    commands.spawn((
+        AudioBundle {
+            source: game_assets.music(event.track),
+            settings: playback_settings.with_volume(Volume::new_relative(0.0)),
+        },
+        Music {
+            track: event.track,
+            fade: 0.0,
+            fading_out: false,
+        },
+        AudioBus::Music,
+    ));
+    if event.track == MusicTrack::Game {
+        let march_beat = march_beat.get_or_insert_with(|| march_beats.add(MarchBeat::default()));
+        commands.spawn((
+            AudioSourceBundle {
+                source: march_beat.clone(),
+                settings: PlaybackSettings::LOOP.with_volume(Volume::new_relative(0.0)),
+            },
+            Music {
+                track: event.track,
+                fade: 0.0,
+                fading_out: false,
+            },
+            IntensityLayer,
+            AudioBus::Music,
+        ));
+    }
+    music_state.current = Some(event.track);
+}
+
+pub fn crossfade_music(
+    mut commands: Commands,
+    time: Res<Time<Real>>,
+    settings: Res<Settings>,
+    music_state: Res<MusicState>,
+    mut music_query: Query<(Entity, &mut Music, Option<&AudioSink>, Has<IntensityLayer>)>,
+) {
+    const CROSSFADE_SECONDS: f32 = 1.5;
+    const MAX_TEMPO_BOOST: f32 = 0.25;
+    const CALM_VOLUME: f32 = 0.7;
+    const LAYER_VOLUME: f32 = 0.6;
+    let step = time.delta_seconds() / CROSSFADE_SECONDS;
+
+    for (entity, mut music, sink, is_layer) in &mut music_query {
+        if music.fading_out {
+            music.fade -= step;
+            if music.fade <= 0.0 {
+                commands.entity(entity).despawn();
+                continue;
             }
+        } else {
+            music.fade = (music.fade + step).min(1.0);
+        }
 
-            // Start game over music.
-            let game_over_music = match game_over_event.won {
-                true => "audio/win-sound.ogg",
-                false => "audio/game-over.ogg",
+        if let Some(sink) = sink {
+            let (speed, intensity_volume) = match music.track {
+                // The layer is silent while calm and fades in as things heat up.
+                MusicTrack::Game if is_layer => (
+                    1.0 + MAX_TEMPO_BOOST * music_state.intensity,
+                    LAYER_VOLUME * music_state.intensity,
+                ),
+                MusicTrack::Game => (
+                    1.0 + MAX_TEMPO_BOOST * music_state.intensity,
+                    CALM_VOLUME + (1.0 - CALM_VOLUME) * music_state.intensity,
+                ),
+                _ => (1.0, 1.0),
             };
-            commands.spawn((
-                AudioBundle {
-                    source: asset_server.load(game_over_music),
-                    settings: PlaybackSettings::DESPAWN,
-                    ..default()
-                },
-                GameOverMusic {},
-            ));
+            sink.set_speed(speed);
+            sink.set_volume(music.fade * intensity_volume * AudioBus::Music.volume(&settings));
         }
-        None => (),
     }
 }
 
 pub fn spawn_player(
     mut loading_flags: ResMut<LoadingFlags>,
     mut commands: Commands,
-    window_query: Query<&Window, With<PrimaryWindow>>,
-    asset_server: Res<AssetServer>,
+    game_assets: Res<GameAssets>,
     mut start_game_event_reader: EventReader<GameStartRequested>,
 ) {
     match start_game_event_reader.read().next() {
         Some(event) => {
-            let player_asset_filename = "sprites/spaceship.png";
-            let window: &Window = window_query.get_single().unwrap();
-
-            let player_height = window.height() / 10.0;
+            let player_height = PLAYFIELD_HEIGHT / 10.0;
 
             commands.spawn((
-                SpriteBundle {
-                    transform: Transform::from_xyz(window.width() / 2.0, player_height, 0.0),
-                    texture: asset_server.load(player_asset_filename),
+                SpriteSheetBundle {
+                    transform: Transform::from_xyz(PLAYFIELD_WIDTH / 2.0, player_height, 0.0),
+                    texture_atlas: game_assets.player_sheet.clone(),
                     ..default()
                 },
                 Player {},
+                SpriteAnimation::new(SpriteClip::PlayerIdle),
                 Sensor,
-                RigidBody::Dynamic,
-                Collider::cuboid(50.0, 50.0),
+                RigidBody::Kinematic,
+                LinearVelocity::ZERO,
+                SpriteCollider::Cuboid,
+                Layer::Player.collision_layers(),
             ));
 
             loading_flags.player = true;
@@ -234,52 +797,170 @@ pub fn spawn_player(
     }
 }
 
+pub fn reset_score(
+    mut score: ResMut<Score>,
+    mut start_game_event_reader: EventReader<GameStartRequested>,
+    mut score_changed_event_writer: EventWriter<ScoreChanged>,
+) {
+    if start_game_event_reader.read().next().is_some() {
+        score.value = 0;
+        score_changed_event_writer.send(ScoreChanged { value: score.value });
+    }
+}
+
 pub fn reset_lives(
     mut lives: ResMut<Lives>,
     mut start_game_event_reader: EventReader<GameStartRequested>,
+    mut lives_changed_event_writer: EventWriter<LivesChanged>,
 ) {
     match start_game_event_reader.read().next() {
         Some(event) => {
             lives.value = NUMBER_OF_LIVES;
+            lives_changed_event_writer.send(LivesChanged { value: lives.value });
         }
         None => (),
     }
 }
 
-pub fn spawn_camera(mut commands: Commands, window_query: Query<&Window, With<PrimaryWindow>>) {
-    let window = window_query.get_single().unwrap();
-
-    commands.spawn(Camera2dBundle {
-        transform: Transform::from_xyz(window.width() / 2.0, window.height() / 2.0, 0.0),
-        ..default()
-    });
-}
-
-pub fn spawn_castles(
-    mut loading_flags: ResMut<LoadingFlags>,
-    mut commands: Commands,
-    window_query: Query<&Window, With<PrimaryWindow>>,
-    asset_server: Res<AssetServer>,
+pub fn reset_wave(
+    mut wave: ResMut<Wave>,
     mut start_game_event_reader: EventReader<GameStartRequested>,
+    mut wave_started_event_writer: EventWriter<WaveStarted>,
 ) {
-    match start_game_event_reader.read().next() {
-        Some(event) => {
-            let window: &Window = window_query.get_single().unwrap();
+    if start_game_event_reader.read().next().is_some() {
+        wave.value = 1;
+        wave_started_event_writer.send(WaveStarted { wave: wave.value });
+    }
+}
 
+pub fn spawn_camera(mut commands: Commands) {
+    // The listener's ears sit at the left and right edge of the playfield.
+    // Only the horizontal position is used for panning, so sounds don't get
+    // quieter the further up the screen they are.
+    //
+    // The camera sits at the usual 2D depth so sprites in front of the
+    // playfield (particles, score popups) aren't behind the near plane, and
+    // the far plane reaches back past the backdrop at negative depths.
+    let origin = vec3(PLAYFIELD_WIDTH / 2.0, PLAYFIELD_HEIGHT / 2.0, 999.9);
+    commands.spawn((
+        Camera2dBundle {
+            projection: OrthographicProjection {
+                scaling_mode: ScalingMode::Fixed {
+                    width: PLAYFIELD_WIDTH,
+                    height: PLAYFIELD_HEIGHT,
+                },
+                far: 2000.0,
+                ..default()
+            },
+            transform: Transform::from_translation(origin),
+            ..default()
+        },
+        SpatialListener::new(PLAYFIELD_WIDTH),
+        CameraShake { trauma: 0.0, origin },
+    ));
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                width: Val::Px(PLAYFIELD_WIDTH),
+                height: Val::Px(PLAYFIELD_HEIGHT),
+                overflow: Overflow::clip(),
+                ..default()
+            },
+            ..default()
+        },
+        PlayfieldUi,
+    ));
+    commands.insert_resource(SpatialScale(vec3(2.0 / PLAYFIELD_WIDTH, 0.0, 0.0)));
+}
+
+/// Scales the playfield to the largest size that fits the window and
+/// centers it, leaving black bars on the sides that don't fit. The UI is
+/// scaled and moved along with it.
+pub fn letterbox_camera(
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    mut camera_query: Query<&mut Camera, With<CameraShake>>,
+    mut playfield_ui_query: Query<&mut Style, With<PlayfieldUi>>,
+    mut ui_scale: ResMut<UiScale>,
+) {
+    let Ok(window) = window_query.get_single() else {
+        return;
+    };
+    let Ok(mut camera) = camera_query.get_single_mut() else {
+        return;
+    };
+
+    let window_size = vec2(
+        window.physical_width() as f32,
+        window.physical_height() as f32,
+    );
+    let scale = (window_size.x / PLAYFIELD_WIDTH).min(window_size.y / PLAYFIELD_HEIGHT);
+    let size = (vec2(PLAYFIELD_WIDTH, PLAYFIELD_HEIGHT) * scale).max(Vec2::ONE);
+    let position = (window_size - size) / 2.0;
+
+    let viewport = Viewport {
+        physical_position: position.as_uvec2(),
+        physical_size: size.as_uvec2(),
+        ..default()
+    };
+
+    // Only touch the camera when the window actually changed size.
+    let unchanged = camera.viewport.as_ref().is_some_and(|current| {
+        current.physical_position == viewport.physical_position
+            && current.physical_size == viewport.physical_size
+    });
+    if unchanged {
+        return;
+    }
+    camera.viewport = Some(viewport);
+
+    // One UI unit is one playfield unit.
+    ui_scale.0 = scale as f64 / window.scale_factor();
+    for mut style in &mut playfield_ui_query {
+        style.left = Val::Px(position.x / scale);
+        style.top = Val::Px(position.y / scale);
+    }
+}
+
+/// Moves newly spawned UI roots into the playfield node.
+pub fn confine_ui_to_playfield(
+    mut commands: Commands,
+    playfield_ui_query: Query<Entity, With<PlayfieldUi>>,
+    root_query: Query<Entity, (With<Node>, Without<Parent>)>,
+) {
+    let Ok(playfield_ui) = playfield_ui_query.get_single() else {
+        return;
+    };
+
+    for root in root_query.iter().filter(|root| *root != playfield_ui) {
+        commands.entity(playfield_ui).add_child(root);
+    }
+}
+
+pub fn spawn_castles(
+    mut loading_flags: ResMut<LoadingFlags>,
+    mut commands: Commands,
+    game_assets: Res<GameAssets>,
+    mut start_game_event_reader: EventReader<GameStartRequested>,
+) {
+    match start_game_event_reader.read().next() {
+        Some(event) => {
             for index in 0..NUMBER_OF_CASTLES {
-                let x = window.width() / (NUMBER_OF_CASTLES + 1) as f32 * (index + 1) as f32;
-                let y = window.height() / 4.0;
+                let x = PLAYFIELD_WIDTH / (NUMBER_OF_CASTLES + 1) as f32 * (index + 1) as f32;
+                let y = PLAYFIELD_HEIGHT / 4.0;
 
                 commands.spawn((
-                    SpriteBundle {
+                    SpriteSheetBundle {
                         transform: Transform::from_xyz(x, y, 0.0),
-                        texture: asset_server.load("sprites/castle.png"),
+                        texture_atlas: game_assets.castle_sheet.clone(),
                         ..default()
                     },
                     Castle { hitpoints: 2 },
+                    SpriteAnimation::new(SpriteClip::CastleIntact),
                     Sensor,
-                    RigidBody::Dynamic,
-                    Collider::cuboid(CASTLE_WIDTH, CASTLE_HEIGHT),
+                    RigidBody::Static,
+                    SpriteCollider::Cuboid,
+                    Layer::Shield.collision_layers(),
                 ));
             }
 
@@ -292,164 +973,336 @@ pub fn spawn_castles(
 pub fn spawn_bullet(
     mut commands: Commands,
     keyboard_input: Res<Input<KeyCode>>,
-    player_query: Query<&mut Transform, With<Player>>,
-    asset_server: Res<AssetServer>,
+    player_query: Query<(Entity, &Transform), With<Player>>,
+    game_assets: Res<GameAssets>,
+    countdown: Res<Countdown>,
+    mut bullet_fired_event_writer: EventWriter<BulletFired>,
     game: Res<Game>,
 ) {
     // Wait until the player presses space
-    if keyboard_input.just_pressed(KeyCode::Space) && *game == Game::STARTED {
+    if keyboard_input.just_pressed(KeyCode::Space)
+        && *game == Game::STARTED
+        && !countdown.is_running()
+    {
         // Get the player position, so we know where to spawn the bullet
-        if let Ok(player) = player_query.get_single() {
+        if let Ok((player_entity, player)) = player_query.get_single() {
             commands.spawn((
-                SpriteBundle {
+                SpriteSheetBundle {
                     transform: Transform::from_xyz(player.translation.x, player.translation.y, 0.0),
-                    texture: asset_server.load("sprites/bullet.png"),
+                    texture_atlas: game_assets.bullet_sheet.clone(),
                     ..default()
                 },
-                Bullet { speed: 500.0 },
+                Bullet { shooter: player_entity },
                 Sensor,
-                RigidBody::Dynamic,
-                Collider::cuboid(15.0, 10.0),
+                RigidBody::Kinematic,
+                LinearVelocity(Vector::new(0.0, BULLET_SPEED)),
+                SpriteCollider::Cuboid,
+                Layer::PlayerProjectile.collision_layers(),
             ));
+            bullet_fired_event_writer.send(BulletFired {
+                shooter: player_entity,
+                position: player.translation,
+                from_player: true,
+            });
         }
+    }
+}
 
-        let bullet_fire = "audio/schieten.ogg";
-        commands.spawn(AudioBundle {
-            source: asset_server.load(bullet_fire),
-            settings: PlaybackSettings::DESPAWN,
-            ..default()
-        });
+/// Cuts each sprite sheet from its image once the image has loaded. Every
+/// sheet is a single cell for now; the art has no frames to cut yet.
+pub fn build_sprite_sheets(
+    game_assets: Res<GameAssets>,
+    images: Res<Assets<Image>>,
+    mut atlases: ResMut<Assets<TextureAtlas>>,
+) {
+    for (image_handle, sheet_handle) in game_assets.sprite_sheets() {
+        if atlases.contains(sheet_handle) {
+            continue;
+        }
+        let Some(image) = images.get(image_handle) else {
+            continue;
+        };
+
+        let sheet =
+            TextureAtlas::from_grid(image_handle.clone(), image.size_f32(), 1, 1, None, None);
+        atlases.insert(sheet_handle, sheet);
     }
 }
 
-pub fn move_bullet(
+/// Gives sprites their collider once the sheet's image is available.
+/// Colliders are cached per image, since bullets of the same kind are spawned
+/// constantly.
+pub fn fit_sprite_colliders(
     mut commands: Commands,
-    mut bullet_query: Query<(&mut Transform, Entity, &mut Bullet), With<Bullet>>,
+    asset_server: Res<AssetServer>,
+    images: Res<Assets<Image>>,
+    atlases: Res<Assets<TextureAtlas>>,
+    collider_overrides: Res<ColliderOverrides>,
+    sprite_query: Query<(Entity, &Handle<TextureAtlas>, &SpriteCollider), Without<Collider>>,
+    mut cache: Local<HashMap<AssetId<Image>, Collider>>,
+) {
+    for (entity, sheet_handle, sprite_collider) in sprite_query.iter() {
+        let Some(sheet) = atlases.get(sheet_handle) else {
+            continue;
+        };
+        let image_handle = &sheet.texture;
+
+        let collider = match cache.get(&image_handle.id()) {
+            Some(collider) => collider.clone(),
+            None => {
+                let Some(image) = images.get(image_handle) else {
+                    continue;
+                };
+
+                let override_shape = asset_server.get_path(image_handle.id()).and_then(|path| {
+                    let key = path.path().to_string_lossy().replace('\\', "/");
+                    collider_overrides.shapes.get(&key).copied()
+                });
+                let shape = override_shape.unwrap_or_else(|| fitted_shape(image, *sprite_collider));
+                let collider = match shape {
+                    ColliderShape::Cuboid { width, height } => Collider::cuboid(width, height),
+                    ColliderShape::Ball { radius } => Collider::ball(radius),
+                };
+                cache.insert(image_handle.id(), collider.clone());
+                collider
+            }
+        };
+
+        commands.entity(entity).insert(collider);
+    }
+}
+
+/// Fits a shape around the opaque pixels of the image, centered on the sprite.
+/// Images that aren't plain RGBA are fitted to their full size instead.
+fn fitted_shape(image: &Image, sprite_collider: SpriteCollider) -> ColliderShape {
+    const ALPHA_THRESHOLD: u8 = 128;
+
+    let (width, height) = (image.width() as usize, image.height() as usize);
+    let center = image.size_f32() / 2.0;
+    let rgba = matches!(
+        image.texture_descriptor.format,
+        TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb
+    );
+
+    let mut half_extents = center;
+    if rgba && image.data.len() == width * height * 4 {
+        half_extents = Vec2::ZERO;
+        for (index, pixel) in image.data.chunks_exact(4).enumerate() {
+            if pixel[3] < ALPHA_THRESHOLD {
+                continue;
+            }
+            let (x, y) = ((index % width) as f32, (index / width) as f32);
+            // Measure from the pixel's outer edge, whichever side of the center it is on.
+            let dx = (x - center.x).abs().max((x + 1.0 - center.x).abs());
+            let dy = (y - center.y).abs().max((y + 1.0 - center.y).abs());
+            half_extents = half_extents.max(Vec2::new(dx, dy));
+        }
+        if half_extents == Vec2::ZERO {
+            half_extents = center;
+        }
+    }
+
+    match sprite_collider {
+        SpriteCollider::Cuboid => ColliderShape::Cuboid {
+            width: half_extents.x * 2.0,
+            height: half_extents.y * 2.0,
+        },
+        SpriteCollider::Ball => ColliderShape::Ball {
+            radius: (half_extents.x + half_extents.y) / 2.0,
+        },
+    }
+}
+
+/// Advances timed animation clips and shows the current frame.
+pub fn animate_sprites(
     time: Res<Time>,
+    mut animation_query: Query<(&mut SpriteAnimation, &mut TextureAtlasSprite)>,
+) {
+    for (mut animation, mut sprite) in &mut animation_query {
+        if animation.clip.fps() > 0.0 {
+            animation.timer.tick(time.delta());
+            for _ in 0..animation.timer.times_finished_this_tick() {
+                animation.step();
+            }
+        }
+
+        let frame = animation.current_frame();
+        sprite.index = frame.index;
+        sprite.flip_x = frame.flip_x;
+        sprite.color = frame.tint;
+    }
+}
+
+/// Starts the shooting, hit and damage clips when those things happen.
+pub fn trigger_animations(
+    mut animation_query: Query<&mut SpriteAnimation>,
+    player_query: Query<Entity, With<Player>>,
+    mut bullet_fired_event_reader: EventReader<BulletFired>,
+    mut player_hit_event_reader: EventReader<PlayerHit>,
+    mut castle_damaged_event_reader: EventReader<CastleDamaged>,
 ) {
-    for bullet in bullet_query.iter_mut() {
-        let mut bullet_transform = bullet.0;
-        let bullet_entity = bullet.1;
-        let bullet_speed = bullet.2.speed;
-        bullet_transform.translation.y += bullet_speed * time.delta_seconds();
+    for event in bullet_fired_event_reader.read() {
+        if event.from_player {
+            continue;
+        }
+        if let Ok(mut animation) = animation_query.get_mut(event.shooter) {
+            animation.play(SpriteClip::EnemyShoot);
+        }
+    }
+
+    if player_hit_event_reader.read().next().is_some() {
+        for player_entity in player_query.iter() {
+            if let Ok(mut animation) = animation_query.get_mut(player_entity) {
+                animation.play(SpriteClip::PlayerHit);
+            }
+        }
+    }
+
+    for event in castle_damaged_event_reader.read() {
+        let clip = SpriteClip::for_castle(event.hitpoints);
+        if let Ok(mut animation) = animation_query.get_mut(event.castle) {
+            if animation.clip != clip {
+                animation.play(clip);
+            }
+        }
+    }
+}
 
-        // Despawn if it's outside the screen
-        if bullet_transform.translation.y > WINDOW_HEIGHT {
-            commands.entity(bullet_entity).despawn();
+/// Shows the engine thrust while the player is moving.
+pub fn animate_player_thrust(
+    mut player_query: Query<(&LinearVelocity, &mut SpriteAnimation), With<Player>>,
+) {
+    for (velocity, mut animation) in &mut player_query {
+        let moving = velocity.x != 0.0;
+        match animation.clip {
+            SpriteClip::PlayerIdle if moving => animation.play(SpriteClip::PlayerThrust),
+            SpriteClip::PlayerThrust if !moving => animation.play(SpriteClip::PlayerIdle),
+            _ => (),
         }
     }
 }
 
-pub fn move_enemy_bullet(
+/// Despawns bullets once they have left the playfield. The physics engine
+/// moves them along their `LinearVelocity`.
+pub fn despawn_offscreen_projectiles(
     mut commands: Commands,
-    mut bullet_query: Query<(&mut Transform, Entity, &mut EnemyBullet), With<EnemyBullet>>,
+    projectile_query: Query<(Entity, &Position), Or<(With<Bullet>, With<EnemyBullet>)>>,
+) {
+    const MARGIN: f32 = 50.0;
+
+    for (projectile_entity, position) in projectile_query.iter() {
+        if position.y > PLAYFIELD_HEIGHT + MARGIN || position.y < -MARGIN {
+            commands.entity(projectile_entity).despawn();
+        }
+    }
+}
+
+/// Fast bullets can cover more than their own length in a frame and skip past
+/// thin colliders. Sweep each bullet's shape along the distance it is about to
+/// travel and report anything in the way as a started collision, so
+/// `resolve_collisions` sees it this frame.
+pub fn sweep_projectiles(
+    spatial_query: SpatialQuery,
+    projectile_query: Query<(
+        Entity,
+        &Collider,
+        &Position,
+        &LinearVelocity,
+        &CollisionLayers,
+        AnyOf<(&Bullet, &EnemyBullet)>,
+    )>,
+    mut collision_started_event_writer: EventWriter<CollisionStarted>,
     time: Res<Time>,
 ) {
-    for bullet in bullet_query.iter_mut() {
-        let mut bullet_transform = bullet.0;
-        let bullet_entity = bullet.1;
-        let bullet_speed = bullet.2.speed;
-        bullet_transform.translation.y -= bullet_speed * time.delta_seconds();
+    for (entity, collider, position, velocity, layers, (bullet, enemy_bullet)) in
+        projectile_query.iter()
+    {
+        let distance = velocity.length() * time.delta_seconds();
+        if distance <= 0.0 {
+            continue;
+        }
 
-        // Despawn if it's outside the screen
-        if bullet_transform.translation.y < 0.5 {
-            commands.entity(bullet_entity).despawn();
+        // Only look for what this bullet's layers would collide with anyway,
+        // skipping whoever fired it.
+        let shooter = match (bullet, enemy_bullet) {
+            (Some(bullet), _) => bullet.shooter,
+            (_, Some(enemy_bullet)) => enemy_bullet.shooter,
+            (None, None) => continue,
+        };
+        let filter = SpatialQueryFilter::new()
+            .with_masks_from_bits(layers.masks_bits())
+            .without_entities([entity, shooter]);
+        if let Some(hit) = spatial_query.cast_shape(
+            collider,
+            position.0,
+            0.0,
+            velocity.normalize(),
+            distance,
+            true,
+            filter,
+        ) {
+            collision_started_event_writer.send(CollisionStarted(entity, hit.entity));
         }
     }
 }
 
 pub fn player_movement(
     keyboard_input: Res<Input<KeyCode>>,
-    mut query: Query<&mut Transform, With<Player>>,
-    time: Res<Time>,
+    countdown: Res<Countdown>,
+    mut query: Query<&mut LinearVelocity, With<Player>>,
 ) {
-    for mut transform in &mut query {
-        let mut direction = Vec3::ZERO;
+    for mut velocity in &mut query {
+        if countdown.is_running() {
+            velocity.0 = Vector::ZERO;
+            continue;
+        }
+
+        let mut direction = Vector::ZERO;
 
         if keyboard_input.pressed(KeyCode::Left) || keyboard_input.pressed(KeyCode::A) {
-            direction += Vec3::new(-1.0, 0.0, 0.0);
+            direction += Vector::new(-1.0, 0.0);
         }
         if keyboard_input.pressed(KeyCode::Right) || keyboard_input.pressed(KeyCode::D) {
-            direction += Vec3::new(1.0, 0.0, 0.0);
+            direction += Vector::new(1.0, 0.0);
         }
 
         if direction.length() > 0.0 {
             direction = direction.normalize()
         }
 
-        transform.translation += direction * PLAYER_SPEED * time.delta_seconds();
+        velocity.0 = direction * PLAYER_SPEED;
     }
 }
 
 pub fn confine_player_movement(
-    mut player_query: Query<&mut Transform, With<Player>>,
-    window_query: Query<&Window, With<PrimaryWindow>>,
+    mut player_query: Query<(&mut Position, &mut LinearVelocity), With<Player>>,
 ) {
-    if let Ok(mut player_transform) = player_query.get_single_mut() {
-        let window = window_query.get_single().unwrap();
-
+    if let Ok((mut position, mut velocity)) = player_query.get_single_mut() {
         let half_sprite_size = PLAYER_SIZE / 2.0;
         let x_min = 0.0 + half_sprite_size;
-        let x_max = window.width() - half_sprite_size;
-
-        let mut translation = player_transform.translation;
-
-        if translation.x < x_min {
-            translation.x = x_min;
-        } else if translation.x > x_max {
-            translation.x = x_max;
+        let x_max = PLAYFIELD_WIDTH - half_sprite_size;
+
+        // Stop at the edge instead of pushing into it every frame.
+        if position.x <= x_min {
+            position.x = x_min;
+            velocity.x = velocity.x.max(0.0);
+        } else if position.x >= x_max {
+            position.x = x_max;
+            velocity.x = velocity.x.min(0.0);
         }
-
-        player_transform.translation = translation;
     }
 }
 
 pub fn spawn_enemies(
     mut commands: Commands,
-    window_query: Query<&Window, With<PrimaryWindow>>,
-    asset_server: Res<AssetServer>,
+    game_assets: Res<GameAssets>,
     enemy_catalog: Res<EnemyCatalog>,
     mut loading_flags: ResMut<LoadingFlags>,
     mut start_game_event_reader: EventReader<GameStartRequested>,
 ) {
     match start_game_event_reader.read().next() {
         Some(_) => {
-            let window = window_query.get_single().unwrap();
-
-            let top_offset = window.height() - ENEMY_SIZE * AMOUNT_OF_ROWS as f32;
-
-            let window_padding = ENEMY_SIZE / 2.0;
-            let window_width = window.width() - window_padding * 2.0;
-
-            let padding_per_enemy =
-                (window_width - (ENEMY_SIZE * AMOUNT_OF_ENEMIES as f32)) / AMOUNT_OF_ENEMIES as f32;
-
-            for i in 0..AMOUNT_OF_ROWS {
-                let level = AMOUNT_OF_ROWS - i;
-                let size = ENEMY_SIZE as f32 + padding_per_enemy;
-                for j in 0..(window.width() / size) as usize {
-                    let new_j = j as f32 * size + window_padding + padding_per_enemy / 2.0;
-                    let enemy = enemy_catalog.get_random_enemy();
-                    commands.spawn((
-                        SpriteBundle {
-                            transform: Transform::from_xyz(
-                                new_j,
-                                top_offset + i as f32 * ENEMY_SIZE,
-                                0.0,
-                            ),
-                            texture: asset_server.load(format!("sprites/{enemy}.png")),
-                            ..default()
-                        },
-                        Enemy {
-                            level,
-                            is_dead: false,
-                        },
-                        Sensor,
-                        RigidBody::Dynamic,
-                        Collider::cuboid(10.0, 10.0),
-                    ));
-                }
-            }
+            spawn_enemy_formation(&mut commands, &game_assets, &enemy_catalog);
 
             loading_flags.enemies = true;
         }
@@ -457,67 +1310,161 @@ pub fn spawn_enemies(
     }
 }
 
+pub fn spawn_enemy_formation(
+    commands: &mut Commands,
+    game_assets: &GameAssets,
+    enemy_catalog: &EnemyCatalog,
+) {
+    let top_offset = PLAYFIELD_HEIGHT - ENEMY_SIZE * AMOUNT_OF_ROWS as f32;
+
+    let window_padding = ENEMY_SIZE / 2.0;
+    let window_width = PLAYFIELD_WIDTH - window_padding * 2.0;
+
+    let padding_per_enemy =
+        (window_width - (ENEMY_SIZE * AMOUNT_OF_ENEMIES as f32)) / AMOUNT_OF_ENEMIES as f32;
+
+    for i in 0..AMOUNT_OF_ROWS {
+        let level = AMOUNT_OF_ROWS - i;
+        let size = ENEMY_SIZE as f32 + padding_per_enemy;
+        for j in 0..(PLAYFIELD_WIDTH / size) as usize {
+            let new_j = j as f32 * size + window_padding + padding_per_enemy / 2.0;
+            let kind = enemy_catalog.get_random_enemy();
+            commands.spawn((
+                SpriteSheetBundle {
+                    transform: Transform::from_xyz(new_j, top_offset + i as f32 * ENEMY_SIZE, 0.0),
+                    texture_atlas: game_assets.enemy_sheets[kind].clone(),
+                    ..default()
+                },
+                Enemy {
+                    level,
+                    is_dead: false,
+                    kind,
+                },
+                SpriteAnimation::new(SpriteClip::EnemyMarch),
+                Sensor,
+                RigidBody::Kinematic,
+                LinearVelocity::ZERO,
+                SpriteCollider::Cuboid,
+                Layer::Enemy.collision_layers(),
+            ));
+        }
+    }
+}
+
 pub fn enemy_movements(
-    mut enemies_query: Query<&mut Transform, With<Enemy>>,
+    mut enemies_query: Query<&mut LinearVelocity, (With<Enemy>, Without<Dying>)>,
     enemy_info: ResMut<EnemyInfo>,
-    time: Res<Time>,
+    countdown: Res<Countdown>,
 ) {
     const STEP: f32 = 50.0;
+
+    let velocity = match enemy_info.stage {
+        _ if countdown.is_running() => Vector::ZERO,
+        EnemyStage::RIGHT => Vector::new(STEP, 0.0),
+        EnemyStage::DOWN(_, _) => Vector::new(0.0, -STEP),
+        EnemyStage::LEFT => Vector::new(-STEP, 0.0),
+    };
+
     for mut enemy in &mut enemies_query {
-        match enemy_info.stage {
-            EnemyStage::RIGHT => {
-                enemy.translation.x += STEP * time.delta_seconds();
-            }
-            EnemyStage::DOWN(_, _) => {
-                enemy.translation.y -= STEP * time.delta_seconds();
-            }
-            EnemyStage::LEFT => {
-                enemy.translation.x -= STEP * time.delta_seconds();
-            }
-        }
+        enemy.0 = velocity;
     }
 }
 
 pub fn enemy_shoot(
     mut commands: Commands,
-    window_query: Query<&Window, With<PrimaryWindow>>,
-    enemies_query: Query<&Transform, With<Enemy>>,
-    asset_server: Res<AssetServer>,
+    enemies_query: Query<(Entity, &Transform), (With<Enemy>, Without<Dying>)>,
+    game_assets: Res<GameAssets>,
+    countdown: Res<Countdown>,
+    mut bullet_fired_event_writer: EventWriter<BulletFired>,
+    game: Res<Game>,
 ) {
-    let window = window_query.get_single().unwrap();
+    if *game != Game::STARTED || countdown.is_running() {
+        return;
+    }
 
-    for enemy in &enemies_query {
+    for (enemy_entity, enemy) in &enemies_query {
         // bullet shoot chance based on height of enemy. between 0 and 1
-        // let shoot_chance = 1.0 - (enemy.translation.y / window.height());
+        // let shoot_chance = 1.0 - (enemy.translation.y / PLAYFIELD_HEIGHT);
         let rnd = rand::random::<f32>();
-        if rnd > (0.9998 - (1.0 - (enemy.translation.y / window.height())) / 1000.0) {
+        if rnd > (0.9998 - (1.0 - (enemy.translation.y / PLAYFIELD_HEIGHT)) / 1000.0) {
             commands.spawn((
-                SpriteBundle {
+                SpriteSheetBundle {
                     transform: Transform::from_xyz(enemy.translation.x, enemy.translation.y, 0.0),
-                    texture: asset_server.load("sprites/bitterbal.png"),
+                    texture_atlas: game_assets.enemy_bullet_sheet.clone(),
                     ..default()
                 },
-                EnemyBullet { speed: 200.0 },
+                EnemyBullet { shooter: enemy_entity },
                 Sensor,
-                RigidBody::Dynamic,
-                Collider::ball(21.0),
+                RigidBody::Kinematic,
+                LinearVelocity(Vector::new(0.0, -ENEMY_BULLET_SPEED)),
+                SpriteCollider::Ball,
+                Layer::EnemyProjectile.collision_layers(),
             ));
-
-            let bullet_fire = "audio/schieten.ogg";
-            commands.spawn(AudioBundle {
-                source: asset_server.load(bullet_fire),
-                settings: PlaybackSettings::ONCE,
-                ..default()
+            bullet_fired_event_writer.send(BulletFired {
+                shooter: enemy_entity,
+                position: enemy.translation,
+                from_player: false,
             });
         }
     }
 }
 
+/// Steps every enemy's march animation together, on a steady beat and
+/// whenever the formation changes direction.
+pub fn march_enemies(
+    time: Res<Time>,
+    enemy_info: Res<EnemyInfo>,
+    countdown: Res<Countdown>,
+    mut enemies_query: Query<&mut SpriteAnimation, With<Enemy>>,
+    mut last_stage: Local<Option<std::mem::Discriminant<EnemyStage>>>,
+    mut since_step: Local<f32>,
+) {
+    const MARCH_STEP_SECONDS: f32 = 0.5;
+
+    if countdown.is_running() {
+        return;
+    }
+
+    let stage = std::mem::discriminant(&enemy_info.stage);
+    *since_step += time.delta_seconds();
+    if *last_stage == Some(stage) && *since_step < MARCH_STEP_SECONDS {
+        return;
+    }
+    *last_stage = Some(stage);
+    *since_step = 0.0;
+
+    for mut animation in &mut enemies_query {
+        if animation.clip == SpriteClip::EnemyMarch {
+            animation.step();
+        }
+    }
+}
+
 pub fn update_enemy_info(
-    window_query: Query<&Window, With<PrimaryWindow>>,
-    enemies_query: Query<&Transform, With<Enemy>>,
+    enemies_query: Query<(&Transform, &Enemy)>,
     mut enemy_info: ResMut<EnemyInfo>,
+    countdown: Res<Countdown>,
+    game: Res<Game>,
 ) {
+    // The step counter below is frame based, so it must not tick while paused
+    // or while the formation is held by the countdown.
+    if *game != Game::STARTED || countdown.is_running() {
+        return;
+    }
+
+    // Dying enemies are no longer part of the formation.
+    let living: Vec<&Transform> = enemies_query
+        .iter()
+        .filter(|(_, enemy)| !enemy.is_dead)
+        .map(|(transform, _)| transform)
+        .collect();
+
+    enemy_info.alive = living.len();
+    enemy_info.min_y = living
+        .iter()
+        .map(|enemy| enemy.translation.y)
+        .fold(f32::MAX, f32::min);
+
     if let EnemyStage::DOWN(down_amount, go_left) = enemy_info.stage {
         enemy_info.stage = if down_amount > 0 {
             EnemyStage::DOWN(down_amount - 1, go_left)
@@ -530,13 +1477,11 @@ pub fn update_enemy_info(
         return;
     }
 
-    let window = window_query.get_single().unwrap();
-
     let mut max_x = f32::MIN;
     let mut min_x = f32::MAX;
     let mut min_y = f32::MAX;
 
-    for enemy in &enemies_query {
+    for enemy in &living {
         if enemy.translation.x > max_x {
             max_x = enemy.translation.x;
         }
@@ -551,10 +1496,8 @@ pub fn update_enemy_info(
     }
 
     const DOWN_AMOUNT: usize = 25; // 15px down
-    const CASTLE_HEIGHT: f32 = 70.0;
 
-    let wall_border = (window.height() / 4.0) + (ENEMY_SIZE / 2.0) + CASTLE_HEIGHT;
-    let stand_still = min_y <= wall_border;
+    let stand_still = min_y <= formation_floor();
 
     let size = ENEMY_SIZE / 2.0;
     if min_x <= 0.5 + size {
@@ -563,7 +1506,7 @@ pub fn update_enemy_info(
         } else {
             EnemyStage::DOWN(DOWN_AMOUNT, false)
         };
-    } else if max_x >= window.width() - 0.5 - size {
+    } else if max_x >= PLAYFIELD_WIDTH - 0.5 - size {
         enemy_info.stage = if stand_still {
             EnemyStage::LEFT
         } else {
@@ -572,220 +1515,1149 @@ pub fn update_enemy_info(
     }
 }
 
-pub fn setup_lives(
-    mut commands: Commands,
-    asset_server: Res<AssetServer>,
-    mut start_game_event_reader: EventReader<GameStartRequested>,
+/// The lowest the enemy formation comes, just above the castles.
+fn formation_floor() -> f32 {
+    const CASTLE_HEIGHT: f32 = 70.0;
+
+    (PLAYFIELD_HEIGHT / 4.0) + (ENEMY_SIZE / 2.0) + CASTLE_HEIGHT
+}
+
+/// Raises the music intensity as enemies die and the formation descends, the
+/// way the march beat of the arcade game speeds up.
+pub fn update_music_intensity(
+    enemy_info: Res<EnemyInfo>,
+    time: Res<Time<Real>>,
+    mut music_state: ResMut<MusicState>,
+    mut wave_size: Local<usize>,
+    mut start_y: Local<f32>,
+    game: Res<Game>,
+    mut wave_started_event_reader: EventReader<WaveStarted>,
 ) {
-    match start_game_event_reader.read().next() {
-        Some(_) => {
-            commands.spawn((
-                TextBundle::from_sections([
-                    TextSection::new(
-                        "Lives: ",
-                        TextStyle {
-                            font: asset_server.load("fonts/Sanspix-Regular.ttf"),
-                            font_size: 30.0,
-                            ..default()
-                        },
-                    ),
-                    TextSection::from_style(TextStyle {
-                        font: asset_server.load("fonts/Sanspix-Regular.ttf"),
-                        font_size: 30.0,
-                        ..default()
-                    }),
-                ])
-                .with_text_alignment(TextAlignment::Center)
-                .with_style(Style {
-                    position_type: PositionType::Absolute,
-                    bottom: Val::Px(5.0),
-                    left: Val::Px(15.0),
-                    ..default()
-                }),
-                LivesCounter,
-            ));
-        }
-        None => (),
+    // Every wave starts calm again.
+    if wave_started_event_reader.read().count() > 0 {
+        *wave_size = 0;
+        *start_y = 0.0;
     }
+
+    let target = match *game {
+        Game::STARTED if enemy_info.alive > 0 => {
+            // The formation only moves down, so the highest point seen this
+            // wave is where it spawned.
+            *wave_size = (*wave_size).max(enemy_info.alive);
+            *start_y = start_y.max(enemy_info.min_y);
+
+            let killed = 1.0 - enemy_info.alive as f32 / *wave_size as f32;
+            let floor = formation_floor();
+            let height = ((enemy_info.min_y - floor) / (*start_y - floor).max(1.0)).clamp(0.0, 1.0);
+            killed.max(1.0 - height)
+        }
+        Game::PAUSED => return,
+        _ => {
+            *wave_size = 0;
+            *start_y = 0.0;
+            0.0
+        }
+    };
+
+    // Ease towards the target so the tempo never jumps.
+    const RESPONSE_SECONDS: f32 = 2.0;
+    let step = time.delta_seconds() / RESPONSE_SECONDS;
+    music_state.intensity += (target - music_state.intensity).clamp(-step, step);
 }
 
-pub fn update_lives(mut query: Query<&mut Text, With<LivesCounter>>, lives: Res<Lives>) {
-    for mut text in &mut query {
-        let value = lives.value;
-        text.sections[1].value = format!("{value}");
-    }
+pub fn spawn_hud(mut commands: Commands, game_assets: Res<GameAssets>, settings: Res<Settings>) {
+    let language = settings.language;
+    let text_style = TextStyle {
+        font: game_assets.font.clone(),
+        font_size: 24.0,
+        ..default()
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::SpaceBetween,
+                    padding: UiRect::axes(Val::Px(15.0), Val::Px(5.0)),
+                    ..default()
+                },
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            Hud,
+        ))
+        .with_children(|parent| {
+            // Top row: score, high score and wave.
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        justify_content: JustifyContent::SpaceBetween,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|row| {
+                    row.spawn((
+                        TextBundle::from_sections([
+                            TextSection::new(hud_label(language, "SCORE"), text_style.clone()),
+                            TextSection::new("0", text_style.clone()),
+                        ]),
+                        ScoreHud,
+                        HudLabel("SCORE"),
+                    ));
+                    row.spawn((
+                        TextBundle::from_sections([
+                            TextSection::new(hud_label(language, "HI"), text_style.clone()),
+                            TextSection::new("0", text_style.clone()),
+                        ]),
+                        HighScoreHud,
+                        HudLabel("HI"),
+                    ));
+                    row.spawn((
+                        TextBundle::from_sections([
+                            TextSection::new(hud_label(language, "WAVE"), text_style.clone()),
+                            TextSection::new("1", text_style.clone()),
+                        ]),
+                        WaveHud,
+                        HudLabel("WAVE"),
+                    ));
+                });
+
+            // Bottom row: one ship icon per life.
+            parent.spawn((
+                NodeBundle {
+                    style: Style {
+                        column_gap: Val::Px(6.0),
+                        ..default()
+                    },
+                    ..default()
+                },
+                LivesHud,
+            ));
+        });
 }
 
-pub fn bullet_hits_enemy(
-    mut commands: Commands,
-    asset_server: Res<AssetServer>,
-    mut collision_query: Query<((Entity, &mut Bullet), &CollidingEntities)>,
-    enemy_query: Query<&Enemy>,
-    mut score: ResMut<Score>,
+pub fn show_hud(
+    mut hud_query: Query<&mut Visibility, With<Hud>>,
+    mut start_game_event_reader: EventReader<GameStartRequested>,
 ) {
-    for ((entity, mut bullet), colliding_entities) in collision_query.iter_mut() {
-        for colliding_entity in colliding_entities.iter() {
-            if enemy_query.get(*colliding_entity).is_ok() {
-                commands.entity(*colliding_entity).despawn();
-                commands.entity(entity).despawn();
-                call_random_hit_sound(commands, asset_server);
-                score.value += 1;
-                return;
-            }
+    if start_game_event_reader.read().next().is_some() {
+        for mut visibility in &mut hud_query {
+            *visibility = Visibility::Visible;
         }
     }
 }
 
-pub fn game_loaded(
-    mut loading_flags: ResMut<LoadingFlags>,
-    mut start_game_event_writer: EventWriter<GameStartRequested>,
-    mut game: ResMut<Game>,
+fn hud_label(language: Language, label: &'static str) -> String {
+    format!("{} ", language.translate(label))
+}
+
+/// Relabels the HUD when the language changes.
+pub fn translate_hud(
+    settings: Res<Settings>,
+    mut hud_query: Query<(&mut Text, &HudLabel)>,
 ) {
-    if *game != Game::LOADING {
+    if !settings.is_changed() {
         return;
     }
 
-    if loading_flags.enemies && loading_flags.castles && loading_flags.player {
-        *game = Game::STARTED;
-        loading_flags.enemies = false;
-        loading_flags.player = false;
-        loading_flags.castles = false;
+    for (mut text, label) in &mut hud_query {
+        text.sections[0].value = hud_label(settings.language, label.0);
     }
 }
 
-pub fn detect_game_won(
-    enemy_query: Query<&Enemy>,
-    score: Res<Score>,
-    mut game_over_event_writer: EventWriter<GameOver>,
-    mut game: ResMut<Game>,
+pub fn update_score_hud(
+    mut score_query: Query<&mut Text, (With<ScoreHud>, Without<HighScoreHud>)>,
+    mut high_score_query: Query<&mut Text, (With<HighScoreHud>, Without<ScoreHud>)>,
+    high_score: Res<HighScore>,
+    mut score_changed_event_reader: EventReader<ScoreChanged>,
+) {
+    if let Some(event) = score_changed_event_reader.read().last() {
+        for mut text in &mut score_query {
+            text.sections[1].value = event.value.to_string();
+        }
+        for mut text in &mut high_score_query {
+            text.sections[1].value = high_score.value.max(event.value).to_string();
+        }
+    }
+}
+
+pub fn update_wave_hud(
+    mut wave_query: Query<&mut Text, With<WaveHud>>,
+    mut wave_started_event_reader: EventReader<WaveStarted>,
+) {
+    if let Some(event) = wave_started_event_reader.read().last() {
+        for mut text in &mut wave_query {
+            text.sections[1].value = event.wave.to_string();
+        }
+    }
+}
+
+pub fn update_lives_hud(
+    mut commands: Commands,
+    game_assets: Res<GameAssets>,
+    lives_query: Query<Entity, With<LivesHud>>,
+    mut lives_changed_event_reader: EventReader<LivesChanged>,
+) {
+    if let Some(event) = lives_changed_event_reader.read().last() {
+        for lives_hud in lives_query.iter() {
+            commands
+                .entity(lives_hud)
+                .despawn_descendants()
+                .with_children(|parent| {
+                    for _ in 0..event.value {
+                        parent.spawn(ImageBundle {
+                            style: Style {
+                                width: Val::Px(24.0),
+                                height: Val::Px(24.0),
+                                ..default()
+                            },
+                            image: game_assets.player.clone().into(),
+                            ..default()
+                        });
+                    }
+                });
+        }
+    }
+}
+
+/// Resolves every contact that started this frame exactly once, whether it
+/// came from the physics engine or from `sweep_projectiles`, and turns it into
+/// gameplay events. A bullet is used up by the first thing it hits, and an
+/// enemy or castle that is already finished off this frame can't be hit again.
+pub fn resolve_collisions(
+    bullet_query: Query<&Bullet>,
+    enemy_bullet_query: Query<&EnemyBullet>,
+    enemy_query: Query<(&Enemy, &Transform)>,
+    player_query: Query<(), With<Player>>,
+    castle_query: Query<(&Castle, &Transform)>,
+    projectile_position_query: Query<&Transform, Or<(With<Bullet>, With<EnemyBullet>)>>,
+    mut collision_started_event_reader: EventReader<CollisionStarted>,
+    mut enemy_killed_event_writer: EventWriter<EnemyKilled>,
+    mut player_hit_event_writer: EventWriter<PlayerHit>,
+    mut castle_damaged_event_writer: EventWriter<CastleDamaged>,
+    mut castle_destroyed_event_writer: EventWriter<CastleDestroyed>,
+    mut projectile_destroyed_event_writer: EventWriter<ProjectileDestroyed>,
+) {
+    let mut used_projectiles = HashSet::new();
+    let mut killed_enemies = HashSet::new();
+    let mut castle_hitpoints = HashMap::new();
+
+    for CollisionStarted(first, second) in collision_started_event_reader.read() {
+        for (projectile, other) in [(*first, *second), (*second, *first)] {
+            if used_projectiles.contains(&projectile) {
+                continue;
+            }
+
+            // Which pairs get here at all is decided by the collision layers,
+            // so the friendly fire branches only run with `FRIENDLY_FIRE` on.
+            let shooter = match (bullet_query.get(projectile), enemy_bullet_query.get(projectile)) {
+                (Ok(bullet), _) => bullet.shooter,
+                (_, Ok(enemy_bullet)) => enemy_bullet.shooter,
+                _ => continue,
+            };
+            if other == shooter {
+                continue;
+            }
+            let from_player = bullet_query.contains(projectile);
+
+            let hit = if let Ok((enemy, enemy_transform)) = enemy_query.get(other) {
+                if enemy.is_dead || !killed_enemies.insert(other) {
+                    continue;
+                }
+                enemy_killed_event_writer.send(EnemyKilled {
+                    enemy: other,
+                    by_player: from_player,
+                    enemy_kind: enemy.kind,
+                    level: enemy.level,
+                    position: enemy_transform.translation,
+                });
+                true
+            } else if player_query.contains(other) {
+                player_hit_event_writer.send(PlayerHit {});
+                true
+            } else if from_player {
+                // Player bullets don't damage the castles.
+                castle_query.contains(other)
+            } else if let Ok((castle, castle_transform)) = castle_query.get(other) {
+                let hitpoints = castle_hitpoints.entry(other).or_insert(castle.hitpoints);
+                if *hitpoints == 0 {
+                    continue;
+                }
+                *hitpoints -= 1;
+                castle_damaged_event_writer.send(CastleDamaged {
+                    castle: other,
+                    hitpoints: *hitpoints,
+                    position: projectile_position_query
+                        .get(projectile)
+                        .map_or(castle_transform.translation, |transform| transform.translation),
+                });
+                if *hitpoints == 0 {
+                    castle_destroyed_event_writer.send(CastleDestroyed {
+                        castle: other,
+                        position: castle_transform.translation,
+                    });
+                }
+                true
+            } else {
+                false
+            };
+
+            if hit {
+                used_projectiles.insert(projectile);
+                if let Ok(transform) = projectile_position_query.get(projectile) {
+                    projectile_destroyed_event_writer.send(ProjectileDestroyed {
+                        projectile,
+                        position: transform.translation,
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// Takes killed enemies out of the fight and sends their portrait spinning
+/// away. `update_dying_enemies` despawns them once that has played out.
+pub fn kill_enemies(
+    mut commands: Commands,
+    mut enemy_query: Query<&mut Enemy>,
+    mut enemy_killed_event_reader: EventReader<EnemyKilled>,
+) {
+    const DEATH_SECONDS: f32 = 0.6;
+
+    for event in enemy_killed_event_reader.read() {
+        let Ok(mut enemy) = enemy_query.get_mut(event.enemy) else {
+            continue;
+        };
+        enemy.is_dead = true;
+
+        let side = if rand::random::<bool>() { 1.0 } else { -1.0 };
+        commands
+            .entity(event.enemy)
+            .remove::<(Collider, SpriteCollider, SpriteAnimation)>()
+            .insert((
+                Dying {
+                    timer: Timer::from_seconds(DEATH_SECONDS, TimerMode::Once),
+                },
+                LinearVelocity(Vector::new(side * 120.0, 160.0)),
+                AngularVelocity(side * -12.0),
+            ));
+    }
+}
+
+pub fn update_dying_enemies(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut dying_query: Query<(Entity, &mut Dying, &mut Transform, &mut TextureAtlasSprite)>,
+) {
+    for (entity, mut dying, mut transform, mut sprite) in &mut dying_query {
+        dying.timer.tick(time.delta());
+        if dying.timer.finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        // Pop: flash bright, then shrink and fade out.
+        let remaining = 1.0 - dying.timer.percent();
+        transform.scale = Vec3::splat(0.4 + 0.8 * remaining);
+        sprite.color = Color::rgba(1.0, 1.0, 0.6 + 0.4 * remaining, remaining);
+    }
+}
+
+/// Scores kills: an enemy is worth its row's level, plus a bonus for every
+/// kill in the current combo.
+pub fn award_score(
+    time: Res<Time>,
+    mut score: ResMut<Score>,
+    mut combo: Local<u32>,
+    mut last_kill: Local<f32>,
+    mut enemy_killed_event_reader: EventReader<EnemyKilled>,
+    mut score_changed_event_writer: EventWriter<ScoreChanged>,
+    mut points_awarded_event_writer: EventWriter<PointsAwarded>,
+) {
+    let mut total = 0;
+    // Enemies shooting each other earn the player nothing.
+    for event in enemy_killed_event_reader.read().filter(|event| event.by_player) {
+        if time.elapsed_seconds() - *last_kill > COMBO_SECONDS {
+            *combo = 0;
+        }
+        *last_kill = time.elapsed_seconds();
+
+        let points = event.level + (*combo).min(MAX_COMBO_BONUS);
+        *combo += 1;
+        total += points;
+        points_awarded_event_writer.send(PointsAwarded {
+            points,
+            position: event.position,
+        });
+    }
+
+    if total > 0 {
+        score.value += total;
+        score_changed_event_writer.send(ScoreChanged { value: score.value });
+    }
+}
+
+pub fn spawn_score_popups(
+    mut commands: Commands,
+    game_assets: Res<GameAssets>,
+    mut points_awarded_event_reader: EventReader<PointsAwarded>,
+) {
+    for event in points_awarded_event_reader.read() {
+        let (font_size, color) = if event.points >= BIG_POPUP_POINTS {
+            (36.0, Color::GOLD)
+        } else {
+            (20.0, Color::WHITE)
+        };
+
+        commands.spawn((
+            Text2dBundle {
+                text: Text::from_section(
+                    format!("+{}", event.points),
+                    TextStyle {
+                        font: game_assets.font.clone(),
+                        font_size,
+                        color,
+                    },
+                ),
+                // In front of the dying enemy (z = 0) and its particles (z = 1).
+                transform: Transform::from_translation(event.position.truncate().extend(5.0)),
+                ..default()
+            },
+            ScorePopup {
+                timer: Timer::from_seconds(0.8, TimerMode::Once),
+            },
+        ));
+    }
+}
+
+pub fn update_score_popups(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut popup_query: Query<(Entity, &mut ScorePopup, &mut Transform, &mut Text)>,
+) {
+    const RISE_SPEED: f32 = 60.0;
+
+    for (entity, mut popup, mut transform, mut text) in &mut popup_query {
+        popup.timer.tick(time.delta());
+        if popup.timer.finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        transform.translation.y += RISE_SPEED * time.delta_seconds();
+        // Stay fully visible for the first half, then fade out.
+        let alpha = (2.0 * (1.0 - popup.timer.percent())).min(1.0);
+        for section in &mut text.sections {
+            section.style.color.set_a(alpha);
+        }
+    }
+}
+
+/// Plays the shot, voice and explosion sounds for gameplay events.
+pub fn play_gameplay_sounds(
+    mut commands: Commands,
+    game_assets: Res<GameAssets>,
+    mut bullet_fired_event_reader: EventReader<BulletFired>,
+    mut enemy_killed_event_reader: EventReader<EnemyKilled>,
+    mut castle_destroyed_event_reader: EventReader<CastleDestroyed>,
+) {
+    for event in bullet_fired_event_reader.read() {
+        play_sound(
+            &mut commands,
+            &game_assets.shot_sound,
+            AudioBus::Sfx,
+            1.0,
+            0.08,
+            Some(event.position),
+        );
+    }
+    for event in enemy_killed_event_reader.read() {
+        play_hit_sound(&mut commands, &game_assets, event.enemy_kind, event.position);
+    }
+    for event in castle_destroyed_event_reader.read() {
+        play_sound(
+            &mut commands,
+            &game_assets.explode_sound,
+            AudioBus::Sfx,
+            1.0,
+            0.05,
+            Some(event.position),
+        );
+    }
+}
+
+/// Places particle emitters where gameplay events happen.
+pub fn spawn_effect_emitters(
+    mut commands: Commands,
+    mut bullet_fired_event_reader: EventReader<BulletFired>,
+    mut enemy_killed_event_reader: EventReader<EnemyKilled>,
+    mut castle_damaged_event_reader: EventReader<CastleDamaged>,
+    mut castle_destroyed_event_reader: EventReader<CastleDestroyed>,
+) {
+    let mut emit = |effect: ParticleEffect, position: Vec3| {
+        commands.spawn((
+            ParticleEmitter { effect },
+            TransformBundle::from_transform(Transform::from_translation(position)),
+        ));
+    };
+
+    for event in bullet_fired_event_reader.read() {
+        if event.from_player {
+            emit(ParticleEffect::MuzzleFlash, event.position + Vec3::Y * PLAYER_SIZE / 2.0);
+        }
+    }
+    for event in enemy_killed_event_reader.read() {
+        emit(ParticleEffect::EnemyBurst, event.position);
+    }
+    for event in castle_damaged_event_reader.read() {
+        emit(ParticleEffect::CastleSparks, event.position);
+    }
+    for event in castle_destroyed_event_reader.read() {
+        emit(ParticleEffect::CastleDebris, event.position);
+    }
+}
+
+/// Turns emitters into particles, reusing expired particles from the pool.
+/// Once `MAX_PARTICLES` are alive, further particles are dropped.
+pub fn emit_particles(
+    mut commands: Commands,
+    mut particle_pool: ResMut<ParticlePool>,
+    emitter_query: Query<(Entity, &ParticleEmitter, &Transform)>,
+) {
+    let mut rng = rand::thread_rng();
+
+    for (emitter_entity, emitter, emitter_transform) in emitter_query.iter() {
+        commands.entity(emitter_entity).despawn();
+
+        let burst = emitter.effect.burst();
+        for _ in 0..burst.count {
+            let particle_entity = match particle_pool.free.pop() {
+                Some(particle_entity) => particle_entity,
+                None if particle_pool.total < MAX_PARTICLES => {
+                    particle_pool.total += 1;
+                    commands.spawn(SpriteBundle::default()).id()
+                }
+                None => break,
+            };
+
+            let angle = burst.direction + rng.gen_range(-0.5..=0.5) * burst.spread;
+            let speed = rng.gen_range(burst.speed.0..=burst.speed.1);
+            let mut translation = emitter_transform.translation;
+            translation.z = 1.0;
+
+            commands.entity(particle_entity).insert((
+                Particle {
+                    velocity: Vec2::from_angle(angle) * speed,
+                    gravity: burst.gravity,
+                    age: 0.0,
+                    lifetime: rng.gen_range(burst.lifetime.0..=burst.lifetime.1),
+                    size: burst.size,
+                    color: burst.color,
+                },
+                Sprite {
+                    color: burst.color.0,
+                    custom_size: Some(Vec2::splat(burst.size.0)),
+                    ..default()
+                },
+                Transform::from_translation(translation),
+                Visibility::Visible,
+            ));
+        }
+    }
+}
+
+/// Moves particles and blends their size and color from start to end over
+/// their lifetime. Expired particles are hidden and go back to the pool.
+pub fn update_particles(
+    time: Res<Time>,
+    mut particle_pool: ResMut<ParticlePool>,
+    mut particle_query: Query<(Entity, &mut Particle, &mut Transform, &mut Sprite, &mut Visibility)>,
+) {
+    let delta = time.delta_seconds();
+
+    for (entity, mut particle, mut transform, mut sprite, mut visibility) in &mut particle_query {
+        // Already back in the pool.
+        if *visibility == Visibility::Hidden {
+            continue;
+        }
+
+        particle.age += delta;
+        if particle.age >= particle.lifetime {
+            *visibility = Visibility::Hidden;
+            particle_pool.free.push(entity);
+            continue;
+        }
+
+        particle.velocity.y += particle.gravity * delta;
+        transform.translation += (particle.velocity * delta).extend(0.0);
+
+        let t = particle.age / particle.lifetime;
+        let (start, end) = particle.color;
+        let [r, g, b, a] = start.as_rgba_f32();
+        let [end_r, end_g, end_b, end_a] = end.as_rgba_f32();
+        sprite.color = Color::rgba(
+            r + (end_r - r) * t,
+            g + (end_g - g) * t,
+            b + (end_b - b) * t,
+            a + (end_a - a) * t,
+        );
+        let size = particle.size.0 + (particle.size.1 - particle.size.0) * t;
+        sprite.custom_size = Some(Vec2::splat(size));
+    }
+}
+
+/// Adds camera trauma, hit-stop and damage flashes for gameplay events, as
+/// far as the settings allow them.
+pub fn trigger_feedback(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    mut hit_stop: ResMut<HitStop>,
+    mut camera_query: Query<&mut CameraShake>,
+    mut enemy_killed_event_reader: EventReader<EnemyKilled>,
+    mut player_hit_event_reader: EventReader<PlayerHit>,
+    mut castle_damaged_event_reader: EventReader<CastleDamaged>,
+    mut castle_destroyed_event_reader: EventReader<CastleDestroyed>,
+) {
+    const HIT_STOP_SECONDS: f32 = 0.15;
+    const FLASH_SECONDS: f32 = 0.3;
+
+    let mut trauma = 0.0;
+    trauma += 0.1 * enemy_killed_event_reader.read().count() as f32;
+    trauma += 0.3 * castle_destroyed_event_reader.read().count() as f32;
+    if player_hit_event_reader.read().next().is_some() {
+        trauma += 0.6;
+        if settings.hit_stop {
+            hit_stop.remaining = HIT_STOP_SECONDS;
+        }
+    }
+
+    for event in castle_damaged_event_reader.read() {
+        trauma += 0.15;
+        // A destroyed castle is despawned this frame; there's nothing to flash.
+        if settings.damage_flash && event.hitpoints > 0 {
+            if let Some(mut castle) = commands.get_entity(event.castle) {
+                castle.insert(DamageFlash {
+                    timer: Timer::from_seconds(FLASH_SECONDS, TimerMode::Once),
+                });
+            }
+        }
+    }
+
+    if settings.screen_shake {
+        for mut camera_shake in &mut camera_query {
+            camera_shake.trauma = (camera_shake.trauma + trauma).min(1.0);
+        }
+    }
+}
+
+/// Offsets the camera by its trauma. Runs on real time so hit-stop and pause
+/// don't freeze the camera off-center.
+pub fn shake_camera(
+    time: Res<Time<Real>>,
+    settings: Res<Settings>,
+    mut camera_query: Query<(&mut CameraShake, &mut Transform)>,
+) {
+    const MAX_OFFSET: f32 = 12.0;
+    const MAX_ROLL: f32 = 0.03;
+    const DECAY_PER_SECOND: f32 = 1.5;
+
+    let mut rng = rand::thread_rng();
+
+    for (mut camera_shake, mut transform) in &mut camera_query {
+        if !settings.screen_shake {
+            camera_shake.trauma = 0.0;
+        }
+        camera_shake.trauma = (camera_shake.trauma - DECAY_PER_SECOND * time.delta_seconds()).max(0.0);
+
+        let shake = camera_shake.trauma * camera_shake.trauma;
+        let offset = Vec2::new(rng.gen_range(-1.0..=1.0), rng.gen_range(-1.0..=1.0)) * MAX_OFFSET * shake;
+        transform.translation = camera_shake.origin + offset.extend(0.0);
+        transform.rotation = Quat::from_rotation_z(rng.gen_range(-1.0..=1.0) * MAX_ROLL * shake);
+    }
+}
+
+/// Slows the game, physics included, right down for a moment after the
+/// player is hit.
+pub fn apply_hit_stop(
+    real_time: Res<Time<Real>>,
+    mut virtual_time: ResMut<Time<Virtual>>,
+    mut physics_time: ResMut<Time<Physics>>,
+    mut hit_stop: ResMut<HitStop>,
+) {
+    const HIT_STOP_SPEED: f32 = 0.05;
+
+    if hit_stop.remaining > 0.0 {
+        hit_stop.remaining -= real_time.delta_seconds();
+        virtual_time.set_relative_speed(HIT_STOP_SPEED);
+        physics_time.set_relative_speed(HIT_STOP_SPEED);
+    } else if virtual_time.relative_speed() != 1.0 {
+        virtual_time.set_relative_speed(1.0);
+        physics_time.set_relative_speed(1.0);
+    }
+}
+
+/// Blinks damaged sprites white. Runs after `animate_sprites`, which sets the
+/// sprite's color every frame.
+pub fn flash_damaged_sprites(
+    mut commands: Commands,
+    real_time: Res<Time<Real>>,
+    mut flash_query: Query<(Entity, &mut DamageFlash, &mut TextureAtlasSprite)>,
+) {
+    const BLINKS_PER_SECOND: f32 = 20.0;
+    // Far above 1.0, so every lit pixel saturates to white.
+    const FLASH_COLOR: Color = Color::rgb(8.0, 8.0, 8.0);
+
+    for (entity, mut flash, mut sprite) in &mut flash_query {
+        flash.timer.tick(real_time.delta());
+        if flash.timer.finished() {
+            commands.entity(entity).remove::<DamageFlash>();
+            continue;
+        }
+
+        let blink = (flash.timer.elapsed_secs() * BLINKS_PER_SECOND) as u32 % 2 == 0;
+        if blink {
+            sprite.color = FLASH_COLOR;
+        }
+    }
+}
+
+pub fn record_game_stats(
+    mut game_stats: ResMut<GameStats>,
+    mut start_game_event_reader: EventReader<GameStartRequested>,
+    mut bullet_fired_event_reader: EventReader<BulletFired>,
+    mut enemy_killed_event_reader: EventReader<EnemyKilled>,
+    mut player_hit_event_reader: EventReader<PlayerHit>,
+    mut castle_destroyed_event_reader: EventReader<CastleDestroyed>,
+    mut game_over_event_reader: EventReader<GameOver>,
+) {
+    if start_game_event_reader.read().next().is_some() {
+        *game_stats = GameStats::default();
+    }
+
+    game_stats.shots_fired += bullet_fired_event_reader
+        .read()
+        .filter(|event| event.from_player)
+        .count() as u32;
+    game_stats.enemies_killed += enemy_killed_event_reader.read().count() as u32;
+    game_stats.lives_lost += player_hit_event_reader.read().count() as u32;
+    game_stats.castles_lost += castle_destroyed_event_reader.read().count() as u32;
+
+    if game_over_event_reader.read().next().is_some() {
+        info!(
+            "Game over: {} shots, {} kills ({:.0}% accuracy), {} lives and {} castles lost",
+            game_stats.shots_fired,
+            game_stats.enemies_killed,
+            game_stats.accuracy(),
+            game_stats.lives_lost,
+            game_stats.castles_lost,
+        );
+    }
+}
+
+pub fn handle_projectile_destroyed(
+    mut commands: Commands,
+    mut projectile_destroyed_event_reader: EventReader<ProjectileDestroyed>,
+) {
+    for event in projectile_destroyed_event_reader.read() {
+        commands.entity(event.projectile).despawn();
+    }
+}
+
+pub fn spawn_loading_screen(
+    mut commands: Commands,
+    game_assets: Res<GameAssets>,
+    settings: Res<Settings>,
+    mut start_game_event_reader: EventReader<GameStartRequested>,
+) {
+    if start_game_event_reader.read().next().is_none() {
+        return;
+    }
+
+    let text_style = TextStyle {
+        font: game_assets.font.clone(),
+        font_size: 30.0,
+        ..default()
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(16.0),
+                    ..default()
+                },
+                background_color: Color::BLACK.into(),
+                z_index: ZIndex::Global(40),
+                ..default()
+            },
+            LoadingScreen,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(settings.language.translate("Loading..."), text_style.clone()),
+                LoadingText,
+            ));
+
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Percent(60.0),
+                        height: Val::Px(20.0),
+                        border: UiRect::all(Val::Px(2.0)),
+                        ..default()
+                    },
+                    border_color: Color::WHITE.into(),
+                    ..default()
+                })
+                .with_children(|bar| {
+                    bar.spawn((
+                        NodeBundle {
+                            style: Style {
+                                width: Val::Percent(0.0),
+                                height: Val::Percent(100.0),
+                                ..default()
+                            },
+                            background_color: Color::ORANGE.into(),
+                            ..default()
+                        },
+                        LoadingBar,
+                    ));
+                });
+        });
+}
+
+pub fn update_loading_screen(
+    asset_server: Res<AssetServer>,
+    game_assets: Res<GameAssets>,
+    settings: Res<Settings>,
+    mut bar_query: Query<&mut Style, With<LoadingBar>>,
+    mut text_query: Query<&mut Text, With<LoadingText>>,
+) {
+    if bar_query.is_empty() {
+        return;
+    }
+
+    let progress = game_assets.load_progress(&asset_server);
+    for mut style in &mut bar_query {
+        style.width = Val::Percent(progress.fraction() * 100.0);
+    }
+
+    if !progress.failed.is_empty() {
+        for mut text in &mut text_query {
+            let failed: Vec<_> = progress
+                .failed
+                .iter()
+                .map(|path| format!("assets/{path}"))
+                .collect();
+            text.sections[0].value = format!(
+                "{}\n{}",
+                settings.language.translate("Failed to load:"),
+                failed.join("\n")
+            );
+            text.sections[0].style.color = Color::RED;
+        }
+    }
+}
+
+pub fn game_loaded(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    game_assets: Res<GameAssets>,
+    loading_screen_query: Query<Entity, With<LoadingScreen>>,
+    mut loading_flags: ResMut<LoadingFlags>,
+    mut countdown_event_writer: EventWriter<CountdownRequested>,
+    mut game: ResMut<Game>,
+) {
+    if *game != Game::LOADING {
+        return;
+    }
+
+    // Don't start until every texture, sound and font is ready, so nothing
+    // pops in after the game has started.
+    let progress = game_assets.load_progress(&asset_server);
+    if !progress.is_done() {
+        return;
+    }
+
+    if loading_flags.enemies && loading_flags.castles && loading_flags.player {
+        for loading_screen in loading_screen_query.iter() {
+            commands.entity(loading_screen).despawn_recursive();
+        }
+        *game = Game::STARTED;
+        countdown_event_writer.send(CountdownRequested {});
+        loading_flags.enemies = false;
+        loading_flags.player = false;
+        loading_flags.castles = false;
+    }
+}
+
+/// Starts a "3, 2, 1, GO" countdown, replacing one that is still running.
+pub fn start_countdown(
+    mut commands: Commands,
+    game_assets: Res<GameAssets>,
+    countdown_screen_query: Query<Entity, With<CountdownScreen>>,
+    enemy_bullet_query: Query<Entity, With<EnemyBullet>>,
+    mut countdown: ResMut<Countdown>,
+    mut countdown_event_reader: EventReader<CountdownRequested>,
+) {
+    if countdown_event_reader.read().last().is_none() {
+        return;
+    }
+
+    for countdown_screen in countdown_screen_query.iter() {
+        commands.entity(countdown_screen).despawn_recursive();
+    }
+
+    // Bullets still in flight would hit the player before they can move.
+    for enemy_bullet in enemy_bullet_query.iter() {
+        commands.entity(enemy_bullet).despawn();
+    }
+
+    countdown.remaining = COUNTDOWN_SECONDS;
+    play_sound(&mut commands, &game_assets.confirm_sound, AudioBus::Sfx, 0.6, 0.0, None);
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                z_index: ZIndex::Global(15),
+                ..default()
+            },
+            CountdownScreen,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font: game_assets.font.clone(),
+                        font_size: 96.0,
+                        color: Color::YELLOW,
+                    },
+                ),
+                CountdownText,
+            ));
+        });
+}
+
+/// Ticks the countdown on the game clock, so it halts while paused. "GO!"
+/// stays on screen for a moment after the enemies are released.
+pub fn update_countdown(
+    mut commands: Commands,
+    time: Res<Time>,
+    settings: Res<Settings>,
+    countdown_screen_query: Query<Entity, With<CountdownScreen>>,
+    mut text_query: Query<&mut Text, With<CountdownText>>,
+    mut countdown: ResMut<Countdown>,
+    game: Res<Game>,
+) {
+    const GO_SECONDS: f32 = 0.75;
+
+    if countdown_screen_query.is_empty() {
+        return;
+    }
+
+    if *game != Game::STARTED && *game != Game::PAUSED {
+        countdown.remaining = 0.0;
+        for countdown_screen in countdown_screen_query.iter() {
+            commands.entity(countdown_screen).despawn_recursive();
+        }
+        return;
+    }
+
+    countdown.remaining -= time.delta_seconds();
+    if countdown.remaining <= -GO_SECONDS {
+        countdown.remaining = 0.0;
+        for countdown_screen in countdown_screen_query.iter() {
+            commands.entity(countdown_screen).despawn_recursive();
+        }
+        return;
+    }
+
+    for mut text in &mut text_query {
+        text.sections[0].value = match countdown.is_running() {
+            true => format!("{}", countdown.remaining.ceil()),
+            false => settings.language.translate("GO!").to_string(),
+        };
+    }
+}
+
+pub fn detect_wave_cleared(
+    enemy_query: Query<&Enemy>,
+    wave: Res<Wave>,
+    mut wave_cleared_event_writer: EventWriter<WaveCleared>,
+    game: Res<Game>,
 ) {
     if *game != Game::STARTED {
         return;
     }
 
-    if enemy_query.is_empty() {
-        *game = Game::ENDED;
-        game_over_event_writer.send(GameOver {
-            won: true,
-            score: score.value,
-        });
+    if enemy_query.iter().all(|enemy| enemy.is_dead) {
+        wave_cleared_event_writer.send(WaveCleared { wave: wave.value });
+    }
+}
+
+/// Clearing the wave wins a classic game; in endless mode the next wave
+/// comes in.
+pub fn handle_wave_cleared(
+    mut commands: Commands,
+    game_assets: Res<GameAssets>,
+    enemy_catalog: Res<EnemyCatalog>,
+    score: Res<Score>,
+    game_mode: Res<GameMode>,
+    mut wave: ResMut<Wave>,
+    mut enemy_info: ResMut<EnemyInfo>,
+    mut wave_cleared_event_reader: EventReader<WaveCleared>,
+    mut game_over_event_writer: EventWriter<GameOver>,
+    mut wave_started_event_writer: EventWriter<WaveStarted>,
+    mut countdown_event_writer: EventWriter<CountdownRequested>,
+    mut game: ResMut<Game>,
+) {
+    if wave_cleared_event_reader.read().last().is_none() || *game != Game::STARTED {
+        return;
+    }
+
+    match *game_mode {
+        GameMode::Classic => {
+            *game = Game::ENDED;
+            game_over_event_writer.send(GameOver {
+                won: true,
+                score: score.value,
+            });
+        }
+        GameMode::Endless => {
+            wave.value += 1;
+            *enemy_info = EnemyInfo::default();
+            spawn_enemy_formation(&mut commands, &game_assets, &enemy_catalog);
+            wave_started_event_writer.send(WaveStarted { wave: wave.value });
+            countdown_event_writer.send(CountdownRequested {});
+        }
     }
 }
 
-pub fn enemy_bullet_hits_player(
-    mut commands: Commands,
-    mut collision_query: Query<((Entity, &mut EnemyBullet), &mut CollidingEntities)>,
-    mut player_query: Query<(Entity, &mut Player)>,
+pub fn handle_player_hit(
     mut lives: ResMut<Lives>,
     score: Res<Score>,
+    mut player_hit_event_reader: EventReader<PlayerHit>,
     mut game_over_event_writer: EventWriter<GameOver>,
+    mut lives_changed_event_writer: EventWriter<LivesChanged>,
+    mut countdown_event_writer: EventWriter<CountdownRequested>,
 ) {
-    for ((bullet_entity, mut bullet), mut colliding_entities) in collision_query.iter_mut() {
-        for player_entity in colliding_entities.iter() {
-            if let Ok((ent, mut cast)) = player_query.get_mut(*player_entity) {
-                commands.entity(bullet_entity).despawn();
-                if lives.value > 0 {
-                    lives.value -= 1;
-                }
-                if lives.value == 0 {
-                    game_over_event_writer.send(GameOver {
-                        won: false,
-                        score: score.value,
-                    });
-                }
-                return;
-            }
+    for _ in player_hit_event_reader.read() {
+        // Hits that land after the last life is gone don't count.
+        if lives.value == 0 {
+            return;
+        }
+
+        lives.value -= 1;
+        lives_changed_event_writer.send(LivesChanged { value: lives.value });
+        if lives.value == 0 {
+            game_over_event_writer.send(GameOver {
+                won: false,
+                score: score.value,
+            });
+        } else {
+            // Give the player a moment to get ready again.
+            countdown_event_writer.send(CountdownRequested {});
         }
     }
 }
 
-pub fn enemy_bullet_hits_castle(
-    mut commands: Commands,
-    mut collision_query: Query<((Entity, &mut EnemyBullet), &mut CollidingEntities)>,
-    mut castle_query: Query<(Entity, &mut Castle)>,
+pub fn handle_castle_damaged(
+    mut castle_query: Query<&mut Castle>,
+    mut castle_damaged_event_reader: EventReader<CastleDamaged>,
 ) {
-    for ((bullet_entity, mut bullet), mut colliding_entities) in collision_query.iter_mut() {
-        for castle_entity in colliding_entities.iter() {
-            if let Ok((ent, mut cast)) = castle_query.get_mut(*castle_entity) {
-                cast.hitpoints -= 1;
-                if cast.hitpoints == 0 {
-                    // Despawn Castle with 0 hitpoints.
-                    commands.entity(ent).despawn();
-                }
-                commands.entity(bullet_entity).despawn();
-                return;
-            }
+    for event in castle_damaged_event_reader.read() {
+        if let Ok(mut castle) = castle_query.get_mut(event.castle) {
+            castle.hitpoints = event.hitpoints;
         }
     }
 }
 
-pub fn bullet_hits_castle(
+pub fn despawn_destroyed_castles(
     mut commands: Commands,
-    mut collision_query: Query<((Entity, &mut Bullet), &CollidingEntities)>,
-    castle_query: Query<&Castle>,
+    mut castle_destroyed_event_reader: EventReader<CastleDestroyed>,
 ) {
-    for ((bullet_entity, mut bullet), colliding_entities) in collision_query.iter_mut() {
-        for castle_entity in colliding_entities.iter() {
-            if castle_query.get(*castle_entity).is_ok() {
-                // Don't mutate the Castle.
-                // commands.entity(*colliding_entity).despawn();
-                commands.entity(bullet_entity).despawn();
-                return;
-            }
-        }
+    for event in castle_destroyed_event_reader.read() {
+        commands.entity(event.castle).despawn();
     }
 }
 
-pub fn call_random_hit_sound(mut commands: Commands, asset_server: Res<AssetServer>) {
-    let mut rng = rand::thread_rng(); // Create a random number generator
-
-    // Generate a random number between 0 and the length of the array (exclusive)
-    let random_index = rng.gen_range(0..GET_HIT_SOUNDS.len());
-
-    let get_hit_sound = GET_HIT_SOUNDS[random_index];
-    commands.spawn(AudioBundle {
-        source: asset_server.load(get_hit_sound),
-        settings: PlaybackSettings::DESPAWN,
-        ..default()
-    });
+/// Plays the voice line of the character that was hit.
+pub fn play_hit_sound(
+    commands: &mut Commands,
+    game_assets: &GameAssets,
+    enemy_kind: usize,
+    position: Vec3,
+) {
+    play_sound(
+        commands,
+        &game_assets.enemy_hit_sounds[enemy_kind],
+        AudioBus::Voice,
+        1.0,
+        0.0,
+        Some(position),
+    );
 }
 
 pub fn handle_game_over(
     mut commands: Commands,
-    window_query: Query<&Window, With<PrimaryWindow>>,
-    asset_server: Res<AssetServer>,
+    game_assets: Res<GameAssets>,
     mut game_over_event_reader: EventReader<GameOver>,
     mut game: ResMut<Game>,
     player_query: Query<Entity, With<Player>>,
     castle_query: Query<Entity, With<Castle>>,
     enemy_query: Query<Entity, With<Enemy>>,
     mut high_score: ResMut<HighScore>,
+    high_score_table: Res<HighScoreTable>,
     score: Res<Score>,
+    settings: Res<Settings>,
 ) {
     match game_over_event_reader.read().next() {
         Some(event) => {
             *game = Game::ENDED;
 
-            let mut screen_asset_filename = "images/game-won.png";
-            let window: &Window = window_query.get_single().unwrap();
+            let mut screen_texture = &game_assets.game_won;
+            if score.value > high_score.value {
+                high_score.value = score.value;
+            }
+            if !event.won {
+                screen_texture = &game_assets.game_lost;
+            }
 
-            if event.won {
-                if score.value > high_score.value {
-                    high_score.value = score.value;
-                }
-            } else {
-                screen_asset_filename = "images/game-lost.png";
+            if high_score_table.qualifies(score.value) {
+                *game = Game::INITIALS;
+                commands.insert_resource(InitialsEntry::new(score.value, settings.language));
+                spawn_initials_entry(&mut commands, &game_assets, settings.language);
             }
 
             commands.spawn((
                 SpriteBundle {
                     transform: Transform::from_xyz(
-                        window.width() / 2.0,
-                        window.height() / 2.0,
+                        PLAYFIELD_WIDTH / 2.0,
+                        PLAYFIELD_HEIGHT / 2.0,
                         0.0,
                     )
                     .with_scale(Vec3::splat(0.25)),
-                    texture: asset_server.load(screen_asset_filename),
+                    texture: screen_texture.clone(),
                     ..default()
                 },
                 GameOverScreen {},
@@ -806,15 +2678,15 @@ pub fn handle_game_over(
             commands.spawn((
                 TextBundle::from_sections([
                     TextSection::new(
-                        format!("Score: {0}", score.value),
+                        format!("{}: {}", settings.language.translate("Score"), score.value),
                         TextStyle {
-                            font: asset_server.load("fonts/Sanspix-Regular.ttf"),
+                            font: game_assets.font.clone(),
                             font_size: 30.0,
                             ..default()
                         },
                     ),
                     TextSection::from_style(TextStyle {
-                        font: asset_server.load("fonts/Sanspix-Regular.ttf"),
+                        font: game_assets.font.clone(),
                         font_size: 30.0,
                         ..default()
                     }),
@@ -836,3 +2708,622 @@ pub fn handle_game_over(
         None => (),
     };
 }
+
+pub fn spawn_initials_entry(commands: &mut Commands, game_assets: &GameAssets, language: Language) {
+    let text_style = TextStyle {
+        font: game_assets.font.clone(),
+        font_size: 30.0,
+        ..default()
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(16.0),
+                    ..default()
+                },
+                background_color: Color::rgba(0.0, 0.0, 0.0, 0.85).into(),
+                z_index: ZIndex::Global(10),
+                ..default()
+            },
+            InitialsScreen,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                language.translate("NEW HIGH SCORE!"),
+                TextStyle {
+                    font_size: 40.0,
+                    color: Color::YELLOW,
+                    ..text_style.clone()
+                },
+            ));
+            parent.spawn(TextBundle::from_section(
+                language.translate("Enter your initials"),
+                text_style.clone(),
+            ));
+
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        column_gap: Val::Px(24.0),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|letters| {
+                    for index in 0..INITIALS_LENGTH {
+                        letters.spawn((
+                            TextBundle::from_section(
+                                "A",
+                                TextStyle {
+                                    font_size: 60.0,
+                                    ..text_style.clone()
+                                },
+                            ),
+                            InitialsLetter { index },
+                        ));
+                    }
+                });
+
+            parent.spawn(TextBundle::from_section(
+                language.translate("Up/Down: letter  Left/Right: move  Enter: confirm"),
+                TextStyle {
+                    font_size: 20.0,
+                    ..text_style.clone()
+                },
+            ));
+        });
+}
+
+pub fn enter_initials(
+    mut commands: Commands,
+    mut keyboard_input: ResMut<Input<KeyCode>>,
+    game_assets: Res<GameAssets>,
+    mut initials_entry: Option<ResMut<InitialsEntry>>,
+    initials_screen_query: Query<Entity, With<InitialsScreen>>,
+    mut high_score_table: ResMut<HighScoreTable>,
+    mut game: ResMut<Game>,
+) {
+    if *game != Game::INITIALS {
+        return;
+    }
+    let Some(entry) = initials_entry.as_mut() else {
+        return;
+    };
+
+    if keyboard_input.any_just_pressed([KeyCode::Up, KeyCode::W]) {
+        entry.cycle_letter(true);
+    }
+    if keyboard_input.any_just_pressed([KeyCode::Down, KeyCode::S]) {
+        entry.cycle_letter(false);
+    }
+    if keyboard_input.any_just_pressed([KeyCode::Left, KeyCode::A]) && entry.cursor > 0 {
+        entry.cursor -= 1;
+    }
+    if keyboard_input.any_just_pressed([KeyCode::Right, KeyCode::D])
+        && entry.cursor < INITIALS_LENGTH - 1
+    {
+        entry.cursor += 1;
+    }
+
+    if keyboard_input.just_pressed(KeyCode::Return) {
+        // Don't let the same key press restart the game.
+        keyboard_input.clear_just_pressed(KeyCode::Return);
+
+        high_score_table.insert(entry.initials(), entry.score);
+        if let Err(error) = high_score_table.save() {
+            error!("Could not save high scores: {error}");
+        }
+
+        for screen in initials_screen_query.iter() {
+            commands.entity(screen).despawn_recursive();
+        }
+        commands.remove_resource::<InitialsEntry>();
+        spawn_high_score_table(&mut commands, &game_assets, &high_score_table, entry.language);
+        *game = Game::ENDED;
+    }
+}
+
+pub fn update_initials_letters(
+    initials_entry: Option<Res<InitialsEntry>>,
+    mut letter_query: Query<(&mut Text, &InitialsLetter)>,
+) {
+    let Some(entry) = initials_entry else {
+        return;
+    };
+    if !entry.is_changed() {
+        return;
+    }
+
+    for (mut text, letter) in &mut letter_query {
+        text.sections[0].value = entry.letters[letter.index].to_string();
+        text.sections[0].style.color = if letter.index == entry.cursor {
+            Color::YELLOW
+        } else {
+            Color::WHITE
+        };
+    }
+}
+
+pub fn gamepad_just_pressed(
+    gamepads: &Gamepads,
+    gamepad_buttons: &Input<GamepadButton>,
+    button_type: GamepadButtonType,
+) -> bool {
+    gamepads
+        .iter()
+        .any(|gamepad| gamepad_buttons.just_pressed(GamepadButton::new(gamepad, button_type)))
+}
+
+pub fn toggle_pause(
+    keyboard_input: Res<Input<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    options_query: Query<(), With<OptionsScreen>>,
+    mut game: ResMut<Game>,
+) {
+    // Escape closes the options screen first.
+    if !options_query.is_empty() {
+        return;
+    }
+
+    let pressed = keyboard_input.any_just_pressed([KeyCode::Escape, KeyCode::P])
+        || gamepad_just_pressed(&gamepads, &gamepad_buttons, GamepadButtonType::Start);
+    if !pressed {
+        return;
+    }
+
+    match *game {
+        Game::STARTED => *game = Game::PAUSED,
+        Game::PAUSED => *game = Game::STARTED,
+        _ => (),
+    }
+}
+
+pub fn pause_on_focus_lost(
+    mut window_focused_event_reader: EventReader<WindowFocused>,
+    mut game: ResMut<Game>,
+) {
+    for event in window_focused_event_reader.read() {
+        if !event.focused && *game == Game::STARTED {
+            *game = Game::PAUSED;
+        }
+    }
+}
+
+/// Freezes the clocks and music whenever the game enters `Game::PAUSED`, and
+/// thaws them again whichever way it leaves.
+pub fn apply_pause(
+    mut commands: Commands,
+    game_assets: Res<GameAssets>,
+    settings: Res<Settings>,
+    game: Res<Game>,
+    mut was_paused: Local<bool>,
+    mut virtual_time: ResMut<Time<Virtual>>,
+    mut physics_time: ResMut<Time<Physics>>,
+    music_query: Query<&AudioSink, With<Music>>,
+    pause_menu_query: Query<Entity, With<PauseMenu>>,
+    options_query: Query<Entity, With<OptionsScreen>>,
+) {
+    let paused = *game == Game::PAUSED;
+    if paused == *was_paused {
+        return;
+    }
+    *was_paused = paused;
+
+    if paused {
+        virtual_time.pause();
+        physics_time.pause();
+        for sink in music_query.iter() {
+            sink.pause();
+        }
+        spawn_pause_menu(&mut commands, &game_assets, settings.language);
+    } else {
+        virtual_time.unpause();
+        physics_time.unpause();
+        for sink in music_query.iter() {
+            sink.play();
+        }
+        for menu in pause_menu_query.iter().chain(options_query.iter()) {
+            commands.entity(menu).despawn_recursive();
+        }
+    }
+}
+
+pub fn spawn_pause_menu(commands: &mut Commands, game_assets: &GameAssets, language: Language) {
+    let text_style = TextStyle {
+        font: game_assets.font.clone(),
+        font_size: 30.0,
+        ..default()
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(16.0),
+                    ..default()
+                },
+                background_color: Color::rgba(0.0, 0.0, 0.0, 0.7).into(),
+                z_index: ZIndex::Global(20),
+                ..default()
+            },
+            PauseMenu { selected: 0 },
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                language.translate("PAUSED"),
+                TextStyle {
+                    font_size: 50.0,
+                    color: Color::YELLOW,
+                    ..text_style.clone()
+                },
+            ));
+
+            for action in PauseAction::ALL {
+                parent.spawn((
+                    TextBundle::from_section(language.translate(action.label()), text_style.clone()),
+                    PauseMenuItem { action },
+                ));
+            }
+        });
+}
+
+pub fn pause_menu_navigation(
+    mut commands: Commands,
+    mut keyboard_input: ResMut<Input<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    game_assets: Res<GameAssets>,
+    mut pause_menu_query: Query<&mut PauseMenu>,
+    options_query: Query<(), With<OptionsScreen>>,
+    game_entity_query: Query<
+        Entity,
+        Or<(
+            With<Player>,
+            With<Enemy>,
+            With<Castle>,
+            With<Bullet>,
+            With<EnemyBullet>,
+            With<GameScreen>,
+        )>,
+    >,
+    mut hud_query: Query<&mut Visibility, With<Hud>>,
+    mut start_game_event_writer: EventWriter<GameStartRequested>,
+    mut play_music_event_writer: EventWriter<PlayMusic>,
+    game_mode: Res<GameMode>,
+    settings: Res<Settings>,
+    mut game: ResMut<Game>,
+) {
+    let Ok(mut pause_menu) = pause_menu_query.get_single_mut() else {
+        return;
+    };
+    if *game != Game::PAUSED || !options_query.is_empty() {
+        return;
+    }
+
+    let count = PauseAction::ALL.len();
+    if keyboard_input.any_just_pressed([KeyCode::Up, KeyCode::W])
+        || gamepad_just_pressed(&gamepads, &gamepad_buttons, GamepadButtonType::DPadUp)
+    {
+        pause_menu.selected = (pause_menu.selected + count - 1) % count;
+    }
+    if keyboard_input.any_just_pressed([KeyCode::Down, KeyCode::S])
+        || gamepad_just_pressed(&gamepads, &gamepad_buttons, GamepadButtonType::DPadDown)
+    {
+        pause_menu.selected = (pause_menu.selected + 1) % count;
+    }
+
+    let confirmed = keyboard_input.any_just_pressed([KeyCode::Return, KeyCode::Space])
+        || gamepad_just_pressed(&gamepads, &gamepad_buttons, GamepadButtonType::South);
+    if !confirmed {
+        return;
+    }
+    // Don't let the same key press start a game or fire a bullet.
+    keyboard_input.clear_just_pressed(KeyCode::Return);
+    keyboard_input.clear_just_pressed(KeyCode::Space);
+
+    match PauseAction::ALL[pause_menu.selected] {
+        PauseAction::Resume => *game = Game::STARTED,
+        PauseAction::Options => spawn_options_screen(&mut commands, &game_assets, &settings),
+        PauseAction::Restart => {
+            for entity in game_entity_query.iter() {
+                commands.entity(entity).despawn();
+            }
+            start_game_event_writer.send(GameStartRequested {});
+            *game = Game::LOADING;
+        }
+        PauseAction::QuitToTitle => {
+            for entity in game_entity_query.iter() {
+                commands.entity(entity).despawn();
+            }
+            for mut visibility in &mut hud_query {
+                *visibility = Visibility::Hidden;
+            }
+            spawn_main_menu(&mut commands, &game_assets, *game_mode, settings.language);
+            play_music_event_writer.send(PlayMusic {
+                track: MusicTrack::Menu,
+            });
+            *game = Game::INTRO;
+        }
+    }
+}
+
+pub fn update_pause_menu_items(
+    pause_menu_query: Query<&PauseMenu, Changed<PauseMenu>>,
+    mut item_query: Query<(&mut Text, &PauseMenuItem)>,
+) {
+    let Ok(pause_menu) = pause_menu_query.get_single() else {
+        return;
+    };
+
+    let selected = PauseAction::ALL[pause_menu.selected];
+    for (mut text, item) in &mut item_query {
+        text.sections[0].style.color = if item.action == selected {
+            Color::YELLOW
+        } else {
+            Color::WHITE
+        };
+    }
+}
+
+pub fn spawn_options_screen(commands: &mut Commands, game_assets: &GameAssets, settings: &Settings) {
+    let text_style = TextStyle {
+        font: game_assets.font.clone(),
+        font_size: 26.0,
+        ..default()
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(10.0),
+                    ..default()
+                },
+                background_color: Color::rgba(0.0, 0.0, 0.0, 0.9).into(),
+                z_index: ZIndex::Global(30),
+                ..default()
+            },
+            OptionsScreen { selected: 0 },
+        ))
+        .with_children(|parent| {
+            parent.spawn(
+                TextBundle::from_section(
+                    settings.language.translate("OPTIONS"),
+                    TextStyle {
+                        font_size: 40.0,
+                        color: Color::YELLOW,
+                        ..text_style.clone()
+                    },
+                )
+                .with_style(Style {
+                    margin: UiRect::bottom(Val::Px(20.0)),
+                    ..default()
+                }),
+            );
+
+            for option in OptionKind::ALL {
+                parent.spawn((
+                    TextBundle::from_section(option_label(option, settings), text_style.clone()),
+                    OptionsItem { option },
+                ));
+            }
+        });
+}
+
+fn option_label(option: OptionKind, settings: &Settings) -> String {
+    let language = settings.language;
+    let slider = |volume: f32| {
+        let filled = (volume * 10.0).round() as usize;
+        format!("[{}{}]", "#".repeat(filled), "-".repeat(10 - filled))
+    };
+    let toggle = |on: bool| language.translate(if on { "On" } else { "Off" });
+
+    let value = match option {
+        OptionKind::MasterVolume => slider(settings.master_volume),
+        OptionKind::MusicVolume => slider(settings.music_volume),
+        OptionKind::SfxVolume => slider(settings.sfx_volume),
+        OptionKind::VoiceVolume => slider(settings.voice_volume),
+        OptionKind::Fullscreen => toggle(settings.fullscreen).to_string(),
+        OptionKind::Vsync => toggle(settings.vsync).to_string(),
+        OptionKind::ScreenShake => toggle(settings.screen_shake).to_string(),
+        OptionKind::HitStop => toggle(settings.hit_stop).to_string(),
+        OptionKind::DamageFlash => toggle(settings.damage_flash).to_string(),
+        OptionKind::Starfield => toggle(settings.starfield).to_string(),
+        OptionKind::Language => language.label().to_string(),
+        OptionKind::Back => return language.translate(option.label()).to_string(),
+    };
+
+    format!("{}: {value}", language.translate(option.label()))
+}
+
+pub fn options_navigation(
+    mut commands: Commands,
+    mut keyboard_input: ResMut<Input<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    game_assets: Res<GameAssets>,
+    mut options_query: Query<(Entity, &mut OptionsScreen)>,
+    mut settings: ResMut<Settings>,
+) {
+    let Ok((options_entity, mut options_screen)) = options_query.get_single_mut() else {
+        return;
+    };
+
+    let count = OptionKind::ALL.len();
+    if keyboard_input.any_just_pressed([KeyCode::Up, KeyCode::W])
+        || gamepad_just_pressed(&gamepads, &gamepad_buttons, GamepadButtonType::DPadUp)
+    {
+        options_screen.selected = (options_screen.selected + count - 1) % count;
+        play_menu_sound(&mut commands, &game_assets, false);
+    }
+    if keyboard_input.any_just_pressed([KeyCode::Down, KeyCode::S])
+        || gamepad_just_pressed(&gamepads, &gamepad_buttons, GamepadButtonType::DPadDown)
+    {
+        options_screen.selected = (options_screen.selected + 1) % count;
+        play_menu_sound(&mut commands, &game_assets, false);
+    }
+
+    let left = keyboard_input.any_just_pressed([KeyCode::Left, KeyCode::A])
+        || gamepad_just_pressed(&gamepads, &gamepad_buttons, GamepadButtonType::DPadLeft);
+    let right = keyboard_input.any_just_pressed([KeyCode::Right, KeyCode::D])
+        || gamepad_just_pressed(&gamepads, &gamepad_buttons, GamepadButtonType::DPadRight);
+    let confirmed = keyboard_input.any_just_pressed([KeyCode::Return, KeyCode::Space])
+        || gamepad_just_pressed(&gamepads, &gamepad_buttons, GamepadButtonType::South);
+    let back = keyboard_input.any_just_pressed([KeyCode::Escape, KeyCode::Back])
+        || gamepad_just_pressed(&gamepads, &gamepad_buttons, GamepadButtonType::East);
+    keyboard_input.clear_just_pressed(KeyCode::Space);
+
+    let option = OptionKind::ALL[options_screen.selected];
+    if back || (confirmed && option == OptionKind::Back) {
+        if let Err(error) = settings.save() {
+            error!("Could not save settings: {error}");
+        }
+        commands.entity(options_entity).despawn_recursive();
+        play_menu_sound(&mut commands, &game_assets, false);
+        return;
+    }
+
+    if !(left || right || confirmed) {
+        return;
+    }
+
+    let step = if left { -0.1 } else { 0.1 };
+    let adjust = |volume: f32| ((volume + step) * 10.0).round().clamp(0.0, 10.0) / 10.0;
+    match option {
+        OptionKind::MasterVolume => settings.master_volume = adjust(settings.master_volume),
+        OptionKind::MusicVolume => settings.music_volume = adjust(settings.music_volume),
+        OptionKind::SfxVolume => settings.sfx_volume = adjust(settings.sfx_volume),
+        OptionKind::VoiceVolume => settings.voice_volume = adjust(settings.voice_volume),
+        OptionKind::Fullscreen => settings.fullscreen = !settings.fullscreen,
+        OptionKind::Vsync => settings.vsync = !settings.vsync,
+        OptionKind::ScreenShake => settings.screen_shake = !settings.screen_shake,
+        OptionKind::HitStop => settings.hit_stop = !settings.hit_stop,
+        OptionKind::DamageFlash => settings.damage_flash = !settings.damage_flash,
+        OptionKind::Starfield => settings.starfield = !settings.starfield,
+        OptionKind::Language => settings.language = settings.language.next(),
+        OptionKind::Back => (),
+    }
+    play_menu_sound(&mut commands, &game_assets, true);
+}
+
+pub fn update_options_items(
+    options_query: Query<Ref<OptionsScreen>>,
+    mut item_query: Query<(&mut Text, &OptionsItem)>,
+    settings: Res<Settings>,
+) {
+    let Ok(options_screen) = options_query.get_single() else {
+        return;
+    };
+    if !options_screen.is_changed() && !settings.is_changed() {
+        return;
+    }
+
+    let selected = OptionKind::ALL[options_screen.selected];
+    for (mut text, item) in &mut item_query {
+        text.sections[0].value = option_label(item.option, &settings);
+        text.sections[0].style.color = if item.option == selected {
+            Color::YELLOW
+        } else {
+            Color::WHITE
+        };
+    }
+}
+
+/// Scales every sound effect and voice line by its bus volume, both when it
+/// starts playing and whenever the settings change. Music is handled by
+/// `crossfade_music`.
+pub fn apply_audio_settings(
+    settings: Res<Settings>,
+    sound_query: Query<(&AudioSink, &PlayingSound, &AudioBus)>,
+    spatial_sound_query: Query<(&SpatialAudioSink, &PlayingSound, &AudioBus)>,
+) {
+    // New sounds already start at the right volume, see `play_sound_effects`.
+    if !settings.is_changed() {
+        return;
+    }
+
+    for (sink, sound, bus) in sound_query.iter() {
+        sink.set_volume(sound.volume * bus.volume(&settings));
+    }
+    for (sink, sound, bus) in spatial_sound_query.iter() {
+        sink.set_volume(sound.volume * bus.volume(&settings));
+    }
+}
+
+pub fn apply_display_settings(
+    settings: Res<Settings>,
+    mut window_query: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+
+    if let Ok(mut window) = window_query.get_single_mut() {
+        let mode = settings.window_mode();
+        if window.mode != mode {
+            window.mode = mode;
+        }
+        let present_mode = settings.present_mode();
+        if window.present_mode != present_mode {
+            window.present_mode = present_mode;
+        }
+    }
+}
+
+/// Runs in `--check-assets` mode: waits for every manifest entry to finish
+/// loading, prints a report and exits.
+pub fn check_assets(
+    asset_server: Res<AssetServer>,
+    game_assets: Res<GameAssets>,
+    asset_check: Res<AssetCheck>,
+    mut app_exit_event_writer: EventWriter<AppExit>,
+) {
+    let load_states: Vec<_> = game_assets
+        .manifest
+        .iter()
+        .map(|(path, handle)| (path, asset_server.load_state(handle.id())))
+        .collect();
+    if load_states
+        .iter()
+        .any(|(_, state)| matches!(state, LoadState::NotLoaded | LoadState::Loading))
+    {
+        return;
+    }
+
+    let mut failed = false;
+    for (path, state) in load_states {
+        if state == LoadState::Failed {
+            failed = true;
+            println!("FAILED  {path}");
+        } else {
+            println!("ok      {path}");
+        }
+    }
+    for path in game_assets.unused_files() {
+        println!("unused  {path}");
+    }
+
+    asset_check.failed.store(failed, Ordering::Relaxed);
+    app_exit_event_writer.send(AppExit);
+}